@@ -1,15 +1,17 @@
-import init, { registerContext, FilterNode, OscillatorNode } from './pkg/waw_demo';
+import init, { registerContext, FilterNode, OscillatorNode, Waveform } from './pkg/waw_demo';
 
 const main = async () => {
   await init()
   const context = await registerContext();
 
-  const osc_1 = new OscillatorNode(context, 110.0)
-  const filter_1 = new FilterNode(context, 440.0)
+  const osc_1 = new OscillatorNode(context, Waveform.Sine)
+  osc_1.frequency.value = 110.0
+  const filter_1 = new FilterNode(context)
+  filter_1.cutoff.value = 440.0
   osc_1.node.connect(filter_1.node);
   filter_1.node.connect(context.destination);
 
-  const frequency = osc_1.node.parameters.get('frequency')!
+  const frequency = osc_1.frequency
 
   const handle_interaction = async () => {
     void context?.resume();
@@ -0,0 +1,11 @@
+//! Writes TypeScript declarations for the demo's processors to `pkg/processors.d.ts`.
+//!
+//! Run natively from the `demo` directory after `wasm-pack build`:
+//! `cargo run --bin declarations`
+
+// Links the processors registered in the library
+use waw_demo as _;
+
+fn main() -> std::io::Result<()> {
+    waw::typescript::write_declarations("pkg/processors.d.ts", "./waw_demo")
+}
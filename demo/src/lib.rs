@@ -1,13 +1,19 @@
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
 use utils::set_panic_hook;
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
 use wasm_bindgen::prelude::wasm_bindgen;
 
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
 use wasm_bindgen_futures::JsFuture;
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
 use web_sys::AudioContext;
 
 pub mod filter;
 pub mod oscillator;
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
 mod utils;
 
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
 async fn polyfill(ctx: &AudioContext) {
     JsFuture::from(
         ctx.audio_worklet()
@@ -19,16 +25,21 @@ async fn polyfill(ctx: &AudioContext) {
     .unwrap();
 }
 
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
 #[wasm_bindgen(js_name = registerContext)]
 /// Create audio context with waw-rs worklets registered
 pub async fn register_context() -> AudioContext {
     let ctx = AudioContext::new().unwrap();
     polyfill(&ctx).await;
-    waw::register_all(&ctx).await.unwrap();
+    waw::register_all(&ctx)
+        .await
+        .and_then(|report| report.into_result())
+        .unwrap();
 
     ctx
 }
 
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
 #[wasm_bindgen(start)]
 pub fn main() {
     set_panic_hook();
@@ -1,133 +1,112 @@
 use wasm_bindgen::prelude::*;
-use waw::{register, AutomationRate, ParameterDescriptor, ParameterValuesRef, Processor};
+use waw::{register, Node, NodeConfig, Parameters, ProcessContext, Processor};
 
-#[derive(Clone)]
+#[derive(Clone, Node)]
+#[node(processor = OscillatorProcessor, class = OscillatorNode)]
 pub struct OscillatorData {
-    pub frequency: f32,
     pub waveform: Waveform,
 }
 
-#[derive(Clone)]
+#[derive(Parameters)]
+#[parameters(node = OscillatorNode)]
+pub struct OscillatorParams<'a> {
+    // Avoid zipper noise when the frequency slider moves
+    #[param(
+        default = 440.0,
+        min = 20.0,
+        max = 20000.0,
+        smoothing = "multiplicative",
+        label = "Frequency",
+        unit = "Hz",
+        scale = "logarithmic"
+    )]
+    pub frequency: &'a [f32],
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
 pub enum Waveform {
     Sine,
     Sawtooth,
     Square,
 }
 
+waw::typescript_binding!(Waveform);
+
 pub struct OscillatorProcessor {
     phase: f32,
-    frequency: f32,
     waveform: Waveform,
 }
 
 impl Processor for OscillatorProcessor {
     type Data = OscillatorData;
+    type Params = OscillatorParams<'static>;
+    type Message = Waveform;
 
     fn new(data: Self::Data) -> Self {
         Self {
             phase: 0.0,
-            frequency: data.frequency,
             waveform: data.waveform,
         }
     }
 
+    fn on_message(&mut self, waveform: Waveform) {
+        self.waveform = waveform;
+    }
+
     fn process(
         &mut self,
         _inputs: &[&[f32]],
         outputs: &mut [&mut [f32]],
-        sample_rate: f32,
-        params: &ParameterValuesRef,
+        context: &ProcessContext,
+        params: &OscillatorParams,
     ) {
         if let Some(output_channel) = outputs.first_mut() {
-            // Get frequency parameter buffer (128 samples)
-            let freq_buffer = params.get("frequency");
-
-            if let Some(freq) = freq_buffer {
-                // Per-sample frequency automation
-                for (i, sample) in output_channel.iter_mut().enumerate() {
-                    let frequency = freq[i];
-                    let phase_increment = frequency / sample_rate;
-
-                    *sample = match self.waveform {
-                        Waveform::Sine => (self.phase * 2.0 * std::f32::consts::PI).sin(),
-                        Waveform::Sawtooth => 2.0 * (self.phase - (self.phase + 0.5).floor()),
-                        Waveform::Square => {
-                            if self.phase < 0.5 {
-                                1.0
-                            } else {
-                                -1.0
-                            }
+            // Per-sample frequency automation
+            for (sample, frequency) in output_channel.iter_mut().zip(params.frequency) {
+                let phase_increment = frequency / context.sample_rate;
+
+                *sample = match self.waveform {
+                    Waveform::Sine => (self.phase * 2.0 * std::f32::consts::PI).sin(),
+                    Waveform::Sawtooth => 2.0 * (self.phase - (self.phase + 0.5).floor()),
+                    Waveform::Square => {
+                        if self.phase < 0.5 {
+                            1.0
+                        } else {
+                            -1.0
                         }
-                    } * 0.3; // Reduce volume
-
-                    self.phase += phase_increment;
-                    if self.phase >= 1.0 {
-                        self.phase -= 1.0;
                     }
-                }
-            } else {
-                // Fallback: use initial frequency
-                let phase_increment = self.frequency / sample_rate;
+                } * 0.3; // Reduce volume
 
-                for sample in output_channel.iter_mut() {
-                    *sample = match self.waveform {
-                        Waveform::Sine => (self.phase * 2.0 * std::f32::consts::PI).sin(),
-                        Waveform::Sawtooth => 2.0 * (self.phase - (self.phase + 0.5).floor()),
-                        Waveform::Square => {
-                            if self.phase < 0.5 {
-                                1.0
-                            } else {
-                                -1.0
-                            }
-                        }
-                    } * 0.3; // Reduce volume
-
-                    self.phase += phase_increment;
-                    if self.phase >= 1.0 {
-                        self.phase -= 1.0;
-                    }
+                self.phase += phase_increment;
+                if self.phase >= 1.0 {
+                    self.phase -= 1.0;
                 }
             }
         }
     }
 
-    fn parameter_descriptors() -> Vec<ParameterDescriptor> {
-        vec![ParameterDescriptor {
-            name: "frequency".to_string(),
-            default_value: 440.0,
-            min_value: 20.0,
-            max_value: 20000.0,
-            automation_rate: AutomationRate::ARate,
-        }]
+    fn fade_out_time(&self) -> Option<f64> {
+        // Longer than the default fade, to avoid a click when the node is dropped
+        Some(0.01)
     }
-}
 
-#[wasm_bindgen]
-pub struct OscillatorNode {
-    wrapper: waw::AudioWorkletNodeWrapper,
+    fn node_config() -> NodeConfig {
+        NodeConfig {
+            number_of_inputs: Some(0),
+            number_of_outputs: Some(1),
+            output_channel_count: Some(vec![1]),
+            ..Default::default()
+        }
+    }
 }
 
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
 #[wasm_bindgen]
 impl OscillatorNode {
-    #[wasm_bindgen(constructor)]
-    pub fn new(ctx: &web_sys::AudioContext, frequency: f32) -> Result<OscillatorNode, JsValue> {
-        let data = OscillatorData {
-            frequency,
-            waveform: Waveform::Sine,
-        };
-
-        // Create options for a generator (0 inputs, 1 output)
-        let options = web_sys::AudioWorkletNodeOptions::new();
-        options.set_number_of_inputs(0);
-        options.set_number_of_outputs(1);
-
-        let wrapper = OscillatorProcessor::create_node(ctx, data, Some(&options))?;
-        Ok(OscillatorNode { wrapper })
-    }
-
-    #[wasm_bindgen(getter)]
-    pub fn node(&self) -> web_sys::AudioWorkletNode {
-        self.wrapper.node().clone()
+    #[wasm_bindgen(js_name = setWaveform)]
+    pub fn set_waveform(&self, waveform: Waveform) -> Result<(), JsValue> {
+        self.wrapper().send(waveform)
     }
 }
 
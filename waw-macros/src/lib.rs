@@ -0,0 +1,571 @@
+//! Procedural macros for `waw`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parenthesized, parse_macro_input, punctuated::Punctuated, Data, DeriveInput, Error, Expr,
+    Fields, Ident, LitStr, Path, Token,
+};
+
+/// Derives `waw::Parameters` for a struct of parameter buffers.
+///
+/// With `#[parameters(node = FilterNode)]`, a getter for each parameter's `AudioParam` is also
+/// added to the node class generated by `#[derive(Node)]`. The getters follow the struct's
+/// fields, and the node's processor must use the struct as its `Params`.
+///
+/// See the `Parameters` trait in `waw` for details.
+#[proc_macro_derive(Parameters, attributes(param, parameters))]
+pub fn derive_parameters(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_parameters(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derives a JS-facing node class for a processor from the processor's `Data` struct.
+///
+/// The generated `#[wasm_bindgen]` class has a constructor taking an audio context, one argument
+/// per field of the struct and optional `AudioWorkletNodeOptions`, a `node` getter, and a static
+/// `parameterDescriptors()` returning the parameter descriptors with their display metadata.
+/// Parameters can be converted to and from normalized `0..1` values with the static
+/// `toNormalized` and `fromNormalized`, and set or read with `setNormalized` and
+/// `getNormalized`. The processor must be registered with `register!`.
+///
+/// Getters for the `AudioParam`s are derived from the processor's parameters, with
+/// `#[parameters(node = ...)]` on its `#[derive(Parameters)]` struct:
+///
+/// ```ignore
+/// #[derive(Node)]
+/// #[node(processor = FilterProcessor, class = FilterNode)]
+/// pub struct FilterData {
+///     pub mode: FilterMode,
+/// }
+///
+/// #[derive(Parameters)]
+/// #[parameters(node = FilterNode)]
+/// pub struct FilterParams<'a> {
+///     #[param(default = 1000.0, min = 20.0, max = 20000.0)]
+///     pub cutoff: &'a [f32],
+/// }
+///
+/// // JS: const filter = new FilterNode(context, FilterMode.LowPass); filter.cutoff.value = 200;
+/// ```
+///
+/// The class is only generated when building for the web; add methods in a separate
+/// `#[wasm_bindgen] impl` block, using `wrapper()` to reach the `AudioWorkletNodeWrapper`.
+#[proc_macro_derive(Node, attributes(node))]
+pub fn derive_node(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_node(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Options given in a `#[node(...)]` attribute.
+#[derive(Default)]
+struct NodeAttrs {
+    processor: Option<Path>,
+    class: Option<Ident>,
+}
+
+impl NodeAttrs {
+    fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut parsed = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("node")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("processor") {
+                    parsed.processor = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("class") {
+                    parsed.class = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("params") {
+                    return Err(meta.error(
+                        "AudioParam getters are derived from the processor's parameters; \
+                         add `#[parameters(node = ...)]` to its `#[derive(Parameters)]` struct",
+                    ));
+                } else {
+                    return Err(meta.error("unsupported node option"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+}
+
+fn expand_node(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let vis = &input.vis;
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "#[derive(Node)] does not support generic structs",
+        ));
+    }
+
+    let attrs = NodeAttrs::parse(&input.attrs)?;
+    let processor = attrs.processor.ok_or_else(|| {
+        Error::new_spanned(
+            ident,
+            "#[derive(Node)] requires `#[node(processor = ..., class = ...)]`",
+        )
+    })?;
+    let class = attrs.class.ok_or_else(|| {
+        Error::new_spanned(
+            ident,
+            "#[derive(Node)] requires `#[node(processor = ..., class = ...)]`",
+        )
+    })?;
+
+    let fields: Vec<_> = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect(),
+            Fields::Unit => Vec::new(),
+            Fields::Unnamed(fields) => {
+                return Err(Error::new_spanned(
+                    fields,
+                    "#[derive(Node)] requires named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                ident,
+                "#[derive(Node)] can only be used on structs",
+            ))
+        }
+    };
+    let field_idents: Vec<_> = fields.iter().map(|field| &field.ident).collect();
+    let field_types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    let data = match &input.data {
+        Data::Struct(data) if matches!(data.fields, Fields::Unit) => quote!(#ident),
+        _ => quote!(#ident { #(#field_idents),* }),
+    };
+
+    let class_doc = format!("JS-facing node for [`{}`].", quote!(#processor));
+
+    Ok(quote! {
+        #[cfg(all(target_family = "wasm", target_os = "unknown"))]
+        #[doc = #class_doc]
+        #[::waw::wasm_bindgen::prelude::wasm_bindgen(wasm_bindgen = ::waw::wasm_bindgen)]
+        #vis struct #class {
+            wrapper: ::waw::AudioWorkletNodeWrapper<#processor>,
+        }
+
+        #[cfg(all(target_family = "wasm", target_os = "unknown"))]
+        #[::waw::wasm_bindgen::prelude::wasm_bindgen(wasm_bindgen = ::waw::wasm_bindgen)]
+        impl #class {
+            /// Creates a new node and its processor.
+            #[wasm_bindgen(constructor)]
+            pub fn new(
+                ctx: &::waw::web_sys::BaseAudioContext,
+                #(#field_idents: #field_types,)*
+                options: ::core::option::Option<::waw::web_sys::AudioWorkletNodeOptions>,
+            ) -> ::core::result::Result<#class, ::waw::wasm_bindgen::JsValue> {
+                let data = #data;
+                let wrapper = ::waw::create_node::<#processor>(
+                    ctx,
+                    <#processor as ::waw::Registered>::NAME,
+                    data,
+                    options.as_ref(),
+                )?;
+                ::core::result::Result::Ok(Self { wrapper })
+            }
+
+            /// The underlying AudioWorkletNode.
+            #[wasm_bindgen(getter)]
+            pub fn node(&self) -> ::waw::web_sys::AudioWorkletNode {
+                self.wrapper.node().clone()
+            }
+
+            /// The processor's parameter descriptors with their display metadata, for
+            /// auto-generated user interfaces.
+            #[wasm_bindgen(js_name = parameterDescriptors)]
+            pub fn parameter_descriptors() -> ::waw::js_sys::Array {
+                <#processor as ::waw::Processor>::parameter_descriptors()
+                    .iter()
+                    .map(::waw::ParameterDescriptor::metadata)
+                    .collect()
+            }
+
+            /// Converts a value of the named parameter to a normalized value in `0..=1`.
+            #[wasm_bindgen(js_name = toNormalized)]
+            pub fn to_normalized(
+                name: &str,
+                value: f32,
+            ) -> ::core::result::Result<f32, ::waw::wasm_bindgen::JsValue> {
+                let descriptor =
+                    ::waw::AudioWorkletNodeWrapper::<#processor>::parameter_descriptor(name)?;
+                ::core::result::Result::Ok(descriptor.to_normalized(value))
+            }
+
+            /// Converts a normalized value in `0..=1` to a value of the named parameter.
+            #[wasm_bindgen(js_name = fromNormalized)]
+            pub fn from_normalized(
+                name: &str,
+                normalized: f32,
+            ) -> ::core::result::Result<f32, ::waw::wasm_bindgen::JsValue> {
+                let descriptor =
+                    ::waw::AudioWorkletNodeWrapper::<#processor>::parameter_descriptor(name)?;
+                ::core::result::Result::Ok(descriptor.from_normalized(normalized))
+            }
+
+            /// Sets the named AudioParam from a normalized value in `0..=1`.
+            #[wasm_bindgen(js_name = setNormalized)]
+            pub fn set_normalized(
+                &self,
+                name: &str,
+                normalized: f32,
+            ) -> ::core::result::Result<(), ::waw::wasm_bindgen::JsValue> {
+                self.wrapper.set_normalized(name, normalized)
+            }
+
+            /// Returns the value of the named AudioParam as a normalized value in `0..=1`.
+            #[wasm_bindgen(js_name = getNormalized)]
+            pub fn normalized(
+                &self,
+                name: &str,
+            ) -> ::core::result::Result<f32, ::waw::wasm_bindgen::JsValue> {
+                self.wrapper.normalized(name)
+            }
+        }
+
+        #[cfg(all(target_family = "wasm", target_os = "unknown"))]
+        impl #class {
+            /// Returns the wrapper around the node, e.g. to send messages to the processor.
+            pub fn wrapper(&self) -> &::waw::AudioWorkletNodeWrapper<#processor> {
+                &self.wrapper
+            }
+        }
+    })
+}
+
+/// Options given in a `#[param(...)]` attribute.
+#[derive(Default)]
+struct ParamAttrs {
+    name: Option<LitStr>,
+    default: Option<Expr>,
+    min: Option<Expr>,
+    max: Option<Expr>,
+    automation_rate: Option<LitStr>,
+    smoothing: Option<LitStr>,
+    smoothing_time: Option<Expr>,
+    label: Option<LitStr>,
+    unit: Option<LitStr>,
+    scale: Option<LitStr>,
+    step: Option<Expr>,
+    choices: Vec<LitStr>,
+    mapping: Option<LitStr>,
+    skew: Option<Expr>,
+    min_db: Option<Expr>,
+}
+
+impl ParamAttrs {
+    fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut parsed = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("param")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    parsed.name = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("default") {
+                    parsed.default = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("min") {
+                    parsed.min = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("max") {
+                    parsed.max = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("automation_rate") {
+                    parsed.automation_rate = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("smoothing") {
+                    parsed.smoothing = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("smoothing_time") {
+                    parsed.smoothing_time = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("label") {
+                    parsed.label = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("unit") {
+                    parsed.unit = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("scale") {
+                    parsed.scale = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("step") {
+                    parsed.step = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("mapping") {
+                    parsed.mapping = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("skew") {
+                    parsed.skew = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("min_db") {
+                    parsed.min_db = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("choices") {
+                    let content;
+                    parenthesized!(content in meta.input);
+                    let choices = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+                    parsed.choices.extend(choices);
+                } else {
+                    return Err(meta.error("unsupported parameter option"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+}
+
+/// Options given in a `#[parameters(...)]` attribute.
+#[derive(Default)]
+struct ParametersAttrs {
+    node: Option<Ident>,
+}
+
+impl ParametersAttrs {
+    fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut parsed = Self::default();
+        for attr in attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("parameters"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("node") {
+                    parsed.node = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("unsupported parameters option"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+}
+
+fn expand_parameters(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let struct_attrs = ParametersAttrs::parse(&input.attrs)?;
+
+    let lifetimes: Vec<_> = input.generics.lifetimes().collect();
+    if lifetimes.len() != 1 || input.generics.params.len() != 1 {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "#[derive(Parameters)] requires a struct with a single lifetime parameter, e.g. `struct Params<'a>`",
+        ));
+    }
+    let lifetime = &lifetimes[0].lifetime;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &data.fields,
+                    "#[derive(Parameters)] requires named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                ident,
+                "#[derive(Parameters)] can only be used on structs",
+            ))
+        }
+    };
+
+    let mut descriptors = Vec::new();
+    let mut values = Vec::new();
+    let mut getters = Vec::new();
+
+    for (index, field) in fields.iter().enumerate() {
+        let field_ident = field.ident.as_ref().expect("named field");
+        let attrs = ParamAttrs::parse(&field.attrs)?;
+
+        let name = attrs
+            .name
+            .unwrap_or_else(|| LitStr::new(&field_ident.to_string(), field_ident.span()));
+        let default = attrs
+            .default
+            .map_or_else(|| quote!(0.0), |expr| quote!(#expr));
+        // Discrete parameters range over the indices of their choices by default
+        let discrete = !attrs.choices.is_empty();
+        let last_choice = attrs.choices.len().saturating_sub(1) as f32;
+        let min = attrs.min.map_or_else(
+            || match discrete {
+                true => quote!(0.0),
+                false => quote!(::core::primitive::f32::MIN),
+            },
+            |expr| quote!(#expr),
+        );
+        let max = attrs.max.map_or_else(
+            || match discrete {
+                true => quote!(#last_choice),
+                false => quote!(::core::primitive::f32::MAX),
+            },
+            |expr| quote!(#expr),
+        );
+        let step_value = match attrs.step {
+            Some(expr) => Some(quote!(#expr)),
+            None if discrete => Some(quote!(1.0)),
+            None => None,
+        };
+        let step = match &step_value {
+            Some(step) => quote!(::core::option::Option::Some(#step)),
+            None => quote!(::core::option::Option::None),
+        };
+        let automation_rate = match attrs.automation_rate {
+            None => format_ident!("ARate"),
+            Some(rate) => match rate.value().as_str() {
+                "a-rate" => format_ident!("ARate"),
+                "k-rate" => format_ident!("KRate"),
+                _ => {
+                    return Err(Error::new_spanned(
+                        rate,
+                        "automation_rate must be \"a-rate\" or \"k-rate\"",
+                    ))
+                }
+            },
+        };
+
+        let smoothing_time = attrs
+            .smoothing_time
+            .map_or_else(|| quote!(0.02), |expr| quote!(#expr));
+        let smoothing = match attrs.smoothing {
+            None => quote!(::waw::Smoothing::None),
+            Some(smoothing) => {
+                let variant =
+                    match smoothing.value().as_str() {
+                        "linear" => format_ident!("Linear"),
+                        "exponential" => format_ident!("Exponential"),
+                        "multiplicative" => format_ident!("Multiplicative"),
+                        _ => return Err(Error::new_spanned(
+                            smoothing,
+                            "smoothing must be \"linear\", \"exponential\" or \"multiplicative\"",
+                        )),
+                    };
+                quote!(::waw::Smoothing::#variant { time: #smoothing_time })
+            }
+        };
+
+        let label = optional_string(attrs.label);
+        let unit = optional_string(attrs.unit);
+        let scale = match attrs.scale {
+            None => format_ident!("Linear"),
+            Some(scale) => match scale.value().as_str() {
+                "linear" => format_ident!("Linear"),
+                "logarithmic" => format_ident!("Logarithmic"),
+                "exponential" => format_ident!("Exponential"),
+                _ => {
+                    return Err(Error::new_spanned(
+                        scale,
+                        "scale must be \"linear\", \"logarithmic\" or \"exponential\"",
+                    ))
+                }
+            },
+        };
+        let choices = &attrs.choices;
+
+        let stepped = {
+            let step = step_value.clone().unwrap_or_else(|| quote!(1.0));
+            quote!(::waw::ParameterMapping::Stepped { step: #step })
+        };
+        let mapping = match attrs.mapping {
+            // Follow the step and scale unless a mapping is given
+            None if step_value.is_some() => stepped,
+            None if scale == "Logarithmic" => quote!(::waw::ParameterMapping::Logarithmic),
+            None => quote!(::waw::ParameterMapping::Linear),
+            Some(mapping) => match mapping.value().as_str() {
+                "linear" => quote!(::waw::ParameterMapping::Linear),
+                "logarithmic" => quote!(::waw::ParameterMapping::Logarithmic),
+                "skewed" => {
+                    let skew = attrs.skew.ok_or_else(|| {
+                        Error::new_spanned(&mapping, "the \"skewed\" mapping requires `skew`")
+                    })?;
+                    quote!(::waw::ParameterMapping::Skewed { skew: #skew })
+                }
+                "decibels" => {
+                    let min_db = attrs
+                        .min_db
+                        .map_or_else(|| quote!(-60.0), |expr| quote!(#expr));
+                    quote!(::waw::ParameterMapping::Decibels { min_db: #min_db })
+                }
+                "stepped" => stepped,
+                _ => return Err(Error::new_spanned(
+                    mapping,
+                    "mapping must be \"linear\", \"logarithmic\", \"skewed\", \"decibels\" or \"stepped\"",
+                )),
+            },
+        };
+
+        descriptors.push(quote! {
+            ::waw::ParameterDescriptor {
+                name: ::std::string::ToString::to_string(#name),
+                default_value: #default,
+                min_value: #min,
+                max_value: #max,
+                automation_rate: ::waw::AutomationRate::#automation_rate,
+                smoothing: #smoothing,
+                label: #label,
+                unit: #unit,
+                scale: ::waw::ParameterScale::#scale,
+                step: #step,
+                choices: ::std::vec![#(::std::string::ToString::to_string(#choices)),*],
+                mapping: #mapping,
+            }
+        });
+        values.push(quote! {
+            #field_ident: ::core::convert::From::from(params.value_index(#index).unwrap_or_default())
+        });
+        let doc = format!("The `{}` AudioParam.", name.value());
+        getters.push(quote! {
+            #[doc = #doc]
+            #[wasm_bindgen(getter, js_name = #name)]
+            pub fn #field_ident(
+                &self,
+            ) -> ::core::result::Result<::waw::web_sys::AudioParam, ::waw::wasm_bindgen::JsValue> {
+                self.wrapper().parameter(#name)
+            }
+        });
+    }
+
+    let node = struct_attrs.node.map(|node| {
+        quote! {
+            #[cfg(all(target_family = "wasm", target_os = "unknown"))]
+            #[::waw::wasm_bindgen::prelude::wasm_bindgen(wasm_bindgen = ::waw::wasm_bindgen)]
+            impl #node {
+                #(#getters)*
+            }
+
+            // The getters only match the node if its processor takes these parameters
+            #[cfg(all(target_family = "wasm", target_os = "unknown"))]
+            const _: fn(&#node) = |node| {
+                fn takes_params<P: ::waw::Processor<Params = #ident<'static>>>(
+                    _: &::waw::AudioWorkletNodeWrapper<P>,
+                ) {
+                }
+                takes_params(node.wrapper());
+            };
+        }
+    });
+
+    Ok(quote! {
+        impl ::waw::Parameters for #ident<'static> {
+            type Values<#lifetime> = #ident<#lifetime>;
+
+            fn descriptors() -> ::std::vec::Vec<::waw::ParameterDescriptor> {
+                ::std::vec![#(#descriptors),*]
+            }
+
+            fn values<#lifetime>(params: ::waw::ParameterValuesRef<#lifetime>) -> #ident<#lifetime> {
+                #ident {
+                    #(#values),*
+                }
+            }
+        }
+
+        #node
+    })
+}
+
+/// Expands an optional string literal to an `Option<String>`.
+fn optional_string(value: Option<LitStr>) -> TokenStream2 {
+    match value {
+        Some(value) => {
+            quote!(::core::option::Option::Some(::std::string::ToString::to_string(#value)))
+        }
+        None => quote!(::core::option::Option::None),
+    }
+}
@@ -1,6 +1,22 @@
+use crate::{
+    parameter::{ParameterDescriptor, Parameters},
+    port::Ports,
+    smoothing::{Smoother, Smoothing},
+};
 use js_sys::{Array, Float32Array, Object, Reflect};
-use std::collections::HashMap;
-use wasm_bindgen::JsCast;
+use std::mem;
+use std::ops::{Deref, DerefMut, Range};
+use wasm_bindgen::{JsCast, JsValue};
+
+/// Number of frames in a Web Audio API render quantum, unless the context was created with
+/// another `renderSizeHint`.
+///
+/// Block sizes are read from the arrays the browser provides for each block, so buffers follow
+/// other sizes too.
+pub const RENDER_QUANTUM_SIZE: usize = 128;
+
+/// Sample rate assumed by parameter smoothing until [`ParameterBuffer::set_sample_rate`] is called.
+const DEFAULT_SAMPLE_RATE: f32 = 48000.0;
 
 /// A generic multi-channel buffer for audio data.
 /// Provides common functionality for managing channel storage and interacting with JS Float32Arrays.
@@ -9,6 +25,11 @@ pub struct ChannelBuffer {
     storage: Vec<Vec<f32>>,
     /// Cached buffer size (number of samples per channel)
     buffer_size: usize,
+    /// Number of channels in each port for the current block.
+    layout: Vec<usize>,
+    /// Pre-allocated table of channel slices, reused by [`ChannelBuffer::refs`] and
+    /// [`ChannelBuffer::refs_mut`]. Always empty between calls.
+    refs: Vec<&'static [f32]>,
 }
 
 impl ChannelBuffer {
@@ -18,6 +39,8 @@ impl ChannelBuffer {
         ChannelBuffer {
             storage,
             buffer_size,
+            layout: vec![num_channels],
+            refs: Vec::with_capacity(num_channels),
         }
     }
 
@@ -36,6 +59,33 @@ impl ChannelBuffer {
         while self.storage.len() < num_channels {
             self.storage.push(vec![0.0; self.buffer_size]);
         }
+        self.refs.reserve(self.storage.len());
+    }
+
+    /// Sets the number of channels in each port, adding new channels if necessary.
+    pub fn set_layout(&mut self, layout: &[usize]) {
+        if self.layout != layout {
+            self.layout.clear();
+            self.layout.extend_from_slice(layout);
+        }
+        self.ensure_channels(self.num_active_channels());
+    }
+
+    /// Reads the number of ports and channels per port from a JS Array of ports,
+    /// adding new channels if necessary.
+    pub fn set_layout_from_js(&mut self, js_array: &Array) {
+        let num_ports = js_array.length() as usize;
+        self.layout.resize(num_ports, 0);
+        for (i, num_channels) in self.layout.iter_mut().enumerate() {
+            let channels: Array = js_array.get(i as u32).unchecked_into();
+            *num_channels = channels.length() as usize;
+        }
+        self.ensure_channels(self.num_active_channels());
+    }
+
+    /// Returns the number of channels in each port.
+    pub fn layout(&self) -> &[usize] {
+        &self.layout
     }
 
     /// Zeros out all channel buffers.
@@ -55,43 +105,67 @@ impl ChannelBuffer {
         self.storage.len()
     }
 
-    /// Returns immutable references to each channel's audio data.
-    pub fn get_refs(&self) -> Vec<&[f32]> {
-        self.storage.iter().map(|v| v.as_slice()).collect()
+    /// Returns the number of channels across all ports in the current layout.
+    pub fn num_active_channels(&self) -> usize {
+        self.layout.iter().sum()
+    }
+
+    /// Returns immutable references to the audio data of each channel in the current layout.
+    /// The slice table is reused between calls, so this does not allocate.
+    pub fn refs(&mut self) -> ChannelRefs<'_, &[f32]> {
+        self.refs_range(0..self.buffer_size)
     }
 
-    /// Returns mutable references to each channel's audio data.
-    pub fn get_mut_refs(&mut self) -> Vec<&mut [f32]> {
-        self.storage.iter_mut().map(|v| v.as_mut_slice()).collect()
+    /// Returns immutable references to the frames in `range` of each channel in the current layout.
+    /// The slice table is reused between calls, so this does not allocate.
+    pub fn refs_range(&mut self, range: Range<usize>) -> ChannelRefs<'_, &[f32]> {
+        let num_channels = self.num_active_channels();
+        let mut refs = recycle(mem::take(&mut self.refs));
+        refs.extend(
+            self.storage[..num_channels]
+                .iter()
+                .map(|v| &v[range.clone()]),
+        );
+        ChannelRefs {
+            refs,
+            layout: &self.layout,
+            cache: &mut self.refs,
+        }
+    }
+
+    /// Returns mutable references to the audio data of each channel in the current layout.
+    /// The slice table is reused between calls, so this does not allocate.
+    pub fn refs_mut(&mut self) -> ChannelRefs<'_, &mut [f32]> {
+        self.refs_mut_range(0..self.buffer_size)
+    }
+
+    /// Returns mutable references to the frames in `range` of each channel in the current layout.
+    /// The slice table is reused between calls, so this does not allocate.
+    pub fn refs_mut_range(&mut self, range: Range<usize>) -> ChannelRefs<'_, &mut [f32]> {
+        let num_channels = self.num_active_channels();
+        let mut refs = recycle(mem::take(&mut self.refs));
+        refs.extend(
+            self.storage[..num_channels]
+                .iter_mut()
+                .map(|v| &mut v[range.clone()]),
+        );
+        ChannelRefs {
+            refs,
+            layout: &self.layout,
+            cache: &mut self.refs,
+        }
     }
 
     /// Copies data from a JS Array to the buffer.
-    /// Automatically detects and adjusts to the buffer size and channel count from JS.
+    /// Automatically detects and adjusts to the buffer size and port layout from JS.
+    /// Without any channel to read the size from, the current buffer size is kept.
     /// Zeros out buffers first, then copies available data.
     pub fn copy_from_js(&mut self, js_array: &Array) {
-        // Count total number of channels in the JS array
-        let mut total_channels = 0;
-        for i in 0..js_array.length() {
-            let channels: Array = js_array.get(i).unchecked_into();
-            total_channels += channels.length() as usize;
-        }
-
-        // Determine the actual buffer size from the first channel
-        let actual_buffer_size = if js_array.length() > 0 {
-            let channels: Array = js_array.get(0).unchecked_into();
-            if channels.length() > 0 {
-                let float_array: Float32Array = channels.get(0).unchecked_into();
-                float_array.length() as usize
-            } else {
-                128
-            }
-        } else {
-            128
-        };
+        let actual_buffer_size = block_size_from_js(js_array).unwrap_or(self.buffer_size);
 
         // Ensure we have enough channels and the right buffer size
         self.ensure_size(actual_buffer_size);
-        self.ensure_channels(total_channels);
+        self.set_layout_from_js(js_array);
 
         // Zero out all buffers first
         self.clear();
@@ -103,15 +177,11 @@ impl ChannelBuffer {
             for j in 0..channels.length() {
                 if channel_idx < self.storage.len() {
                     let float_array: Float32Array = channels.get(j).unchecked_into();
-                    let length = float_array.length() as usize;
-
-                    let copy_len = length.min(actual_buffer_size);
-                    float_array.copy_to(&mut self.storage[channel_idx][..copy_len]);
-
-                    // If JS provided less data than expected, the rest is already zeroed
-                    if length < actual_buffer_size {
-                        self.storage[channel_idx][length..].fill(0.0);
-                    }
+                    copy_channel_in(
+                        float_array.length() as usize,
+                        &mut self.storage[channel_idx][..actual_buffer_size],
+                        |block| copy_prefix_to(&float_array, block),
+                    );
 
                     channel_idx += 1;
                 }
@@ -119,7 +189,33 @@ impl ChannelBuffer {
         }
     }
 
-    /// Copies data from the buffer to a JS Array
+    /// Copies data from a slice of channels to the buffer, following the current layout.
+    /// Zeros out buffers first, then copies available data.
+    /// Channels longer than the buffer size are truncated.
+    pub fn copy_from_slices(&mut self, channels: &[&[f32]]) {
+        let num_channels = self.num_active_channels();
+        self.clear();
+
+        for (storage, channel) in self.storage[..num_channels].iter_mut().zip(channels) {
+            copy_channel_in(channel.len(), &mut storage[..self.buffer_size], |block| {
+                block.copy_from_slice(&channel[..block.len()])
+            });
+        }
+    }
+
+    /// Copies data from the buffer to a slice of channels.
+    /// Channels shorter than the buffer size receive its first frames.
+    pub fn copy_to_slices(&self, channels: &mut [&mut [f32]]) {
+        let num_channels = self.num_active_channels();
+        for (channel, storage) in channels.iter_mut().zip(&self.storage[..num_channels]) {
+            copy_channel_out(channel.len(), &storage[..self.buffer_size], |block| {
+                channel[..block.len()].copy_from_slice(block)
+            });
+        }
+    }
+
+    /// Copies data from the buffer to a JS Array.
+    /// Channels shorter than the buffer size receive its first frames.
     pub fn copy_to_js(&self, js_array: &Array) {
         let mut channel_idx = 0;
         for i in 0..js_array.length() {
@@ -127,8 +223,11 @@ impl ChannelBuffer {
             for j in 0..channels.length() {
                 if channel_idx < self.storage.len() {
                     let float_array: Float32Array = channels.get(j).into();
-
-                    float_array.copy_from(&self.storage[channel_idx]);
+                    copy_channel_out(
+                        float_array.length() as usize,
+                        &self.storage[channel_idx][..self.buffer_size],
+                        |block| copy_prefix_from(&float_array, block),
+                    );
 
                     channel_idx += 1;
                 }
@@ -137,38 +236,212 @@ impl ChannelBuffer {
     }
 }
 
-/// Copies data from a JS Float32Array to a Rust Vec<f32> buffer.
+/// Fills a channel buffer, sized to the block, from a channel of `length` frames.
+/// `copy` fills its argument with the first frames of the channel; frames past the end of
+/// the channel are zeroed.
+///
+/// Never panics: channels of any length are truncated or padded, as a panic would take down
+/// the whole worklet.
+fn copy_channel_in(length: usize, storage: &mut [f32], copy: impl FnOnce(&mut [f32])) {
+    let (head, tail) = storage.split_at_mut(length.min(storage.len()));
+    copy(head);
+    tail.fill(0.0);
+}
+
+/// Copies a channel buffer to a channel of `length` frames. `copy` writes its argument to the
+/// first frames of the channel. Never panics, like [`copy_channel_in`].
+fn copy_channel_out(length: usize, storage: &[f32], copy: impl FnOnce(&[f32])) {
+    copy(&storage[..length.min(storage.len())]);
+}
+
+/// Returns the number of frames in the block from the first channel of a JS Array of ports,
+/// or `None` if no port has any channel.
+pub(crate) fn block_size_from_js(js_array: &Array) -> Option<usize> {
+    js_array.iter().find_map(|port| {
+        let channels: Array = port.unchecked_into();
+        (channels.length() > 0).then(|| {
+            let float_array: Float32Array = channels.get(0).unchecked_into();
+            float_array.length() as usize
+        })
+    })
+}
+
+/// A table of channel slices borrowed from a [`ChannelBuffer`].
+///
+/// Dereferences to `[&[f32]]` or `[&mut [f32]]`, and hands its allocation back
+/// to the buffer when dropped.
+pub struct ChannelRefs<'a, T> {
+    refs: Vec<T>,
+    layout: &'a [usize],
+    cache: &'a mut Vec<&'static [f32]>,
+}
+
+impl<T> ChannelRefs<'_, T> {
+    /// Returns the channels grouped by port.
+    pub fn ports(&mut self) -> Ports<'_, T> {
+        Ports::new(&mut self.refs, self.layout)
+    }
+}
+
+impl<T> Deref for ChannelRefs<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        &self.refs
+    }
+}
+
+impl<T> DerefMut for ChannelRefs<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.refs
+    }
+}
+
+impl<T> Drop for ChannelRefs<'_, T> {
+    fn drop(&mut self) {
+        *self.cache = recycle(mem::take(&mut self.refs));
+    }
+}
+
+/// Empties a `Vec` and reuses its allocation for an element type with the same layout.
+///
+/// Only used between slice references (`&[f32]`, `&mut [f32]`), which share the layout of a
+/// fat pointer; this is checked at compile time.
+fn recycle<T, U>(mut vec: Vec<T>) -> Vec<U> {
+    const {
+        assert!(mem::size_of::<T>() == mem::size_of::<U>());
+        assert!(mem::align_of::<T>() == mem::align_of::<U>());
+    }
+
+    vec.clear();
+    let mut vec = mem::ManuallyDrop::new(vec);
+    // SAFETY: The allocation was made for `capacity` values of `T`, which has the same size
+    // and alignment as `U`, and the vector is empty so no value is reinterpreted. Ownership of
+    // the allocation moves to the new vector, as the old one is never dropped.
+    unsafe { Vec::from_raw_parts(vec.as_mut_ptr().cast::<U>(), 0, vec.capacity()) }
+}
+
+/// A typed array of `f32` values, such as a JS `Float32Array`.
+///
+/// Copies between typed arrays and slices require equal lengths; taking a subarray of the right
+/// length creates a new JS object, so it is only done when the lengths differ.
+trait FloatArray: Sized {
+    fn len(&self) -> usize;
+    /// Returns a view of the first `len` values.
+    fn prefix(&self, len: usize) -> Self;
+    fn copy_to(&self, dst: &mut [f32]);
+    fn copy_from(&self, src: &[f32]);
+}
+
+impl FloatArray for Float32Array {
+    fn len(&self) -> usize {
+        self.length() as usize
+    }
+
+    fn prefix(&self, len: usize) -> Self {
+        self.subarray(0, len as u32)
+    }
+
+    fn copy_to(&self, dst: &mut [f32]) {
+        Float32Array::copy_to(self, dst);
+    }
+
+    fn copy_from(&self, src: &[f32]) {
+        Float32Array::copy_from(self, src);
+    }
+}
+
+/// Fills `block` with the first values of `array`, which must hold at least as many.
+fn copy_prefix_to<A: FloatArray>(array: &A, block: &mut [f32]) {
+    if array.len() == block.len() {
+        array.copy_to(block);
+    } else {
+        array.prefix(block.len()).copy_to(block);
+    }
+}
+
+/// Writes `block` to the first values of `array`, which must hold at least as many.
+fn copy_prefix_from<A: FloatArray>(array: &A, block: &[f32]) {
+    if array.len() == block.len() {
+        array.copy_from(block);
+    } else {
+        array.prefix(block.len()).copy_from(block);
+    }
+}
+
+/// Copies data from a JS Float32Array to a parameter buffer.
 /// Handles Web Audio API parameter buffer semantics.
-fn copy_param_from_js(js_array: &Float32Array, buffer: &mut Vec<f32>) {
-    // Ensure buffer is sized to 128 samples (Web Audio render quantum size)
-    buffer.resize(128, 0.0);
+/// Returns `true` if the value is constant for the block.
+fn copy_param_from_js(js_array: &Float32Array, buffer: &mut [f32]) -> bool {
+    copy_param(
+        js_array.length() as usize,
+        buffer,
+        |block| copy_prefix_to(js_array, block),
+        || js_array.get_index(0),
+    )
+}
 
-    match js_array.length() {
-        // If the automation rate of the parameter is "a-rate", the array will contain 128 values
-        // — one for each frame in the current audio block.
-        128 => {
-            js_array.copy_to(buffer.as_mut());
-        }
+/// Copies data from a slice of parameter values to a parameter buffer.
+/// Uses the same semantics as [`copy_param_from_js`].
+fn copy_param_from_slice(values: &[f32], buffer: &mut [f32]) -> bool {
+    copy_param(
+        values.len(),
+        buffer,
+        |block| block.copy_from_slice(&values[..block.len()]),
+        || values[0],
+    )
+}
+
+/// Fills a parameter buffer, sized to the block, from a source of `length` values.
+/// `copy` fills its argument with the first values of the source, `first` reads the single
+/// value of a constant block. Returns `true` if the value is constant for the block.
+///
+/// Never panics: unexpected lengths are handled as well as possible, as a panic would take down
+/// the whole worklet.
+fn copy_param(
+    length: usize,
+    buffer: &mut [f32],
+    copy: impl FnOnce(&mut [f32]),
+    first: impl FnOnce() -> f32,
+) -> bool {
+    match length {
+        // An empty array carries no values: keep the previous ones.
+        0 => false,
 
         // If the automation rate is "k-rate", the array will contain a single value,
-        // which is to be used for each of 128 frames.
+        // which is to be used for each frame.
         //
         // If there's no automation happening during the time represented by the current block,
         // the array may contain a single value that is constant for the entire block,
-        // instead of 128 identical values.
+        // instead of identical values for each frame.
         1 => {
-            buffer.fill(js_array.get_index(0));
+            buffer.fill(first());
+            true
         }
 
-        // Other possibilities are not supported.
-        other => {
-            panic!(
-                "Float32Array length {other} not supported. Expected 1 (k-rate) or 128 (a-rate)."
-            );
+        // If the automation rate of the parameter is "a-rate", the array will contain one value
+        // for each frame in the current audio block. Extra values are ignored.
+        length if length >= buffer.len() => {
+            copy(buffer);
+            false
+        }
+
+        // Fewer values than frames: hold the last value until the end of the block.
+        length => {
+            let (head, tail) = buffer.split_at_mut(length);
+            copy(head);
+            tail.fill(head[length - 1]);
+            false
         }
     }
 }
 
+/// Resizes a parameter buffer, filling new frames with its last value.
+fn resize_holding_last(buffer: &mut Vec<f32>, len: usize) {
+    let last = buffer.last().copied().unwrap_or(0.0);
+    buffer.resize(len, last);
+}
+
 /// A buffer that holds input audio data for processing, organized as a vector of channels.
 pub struct InputBuffer {
     inner: ChannelBuffer,
@@ -189,9 +462,27 @@ impl InputBuffer {
         self.inner.copy_from_js(inputs);
     }
 
+    /// Fills the buffer with data from a slice of input channels, grouped into ports by `layout`.
+    /// Zeros out buffers first, then copies available data.
+    /// If a channel is shorter than the buffer size, remaining space stays zeroed.
+    pub fn fill_from_slices(&mut self, inputs: &[&[f32]], layout: &[usize]) {
+        self.inner.set_layout(layout);
+        self.inner.copy_from_slices(inputs);
+    }
+
+    /// Ensures buffers match the expected size, resizing if necessary.
+    pub fn ensure_size(&mut self, buffer_size: usize) {
+        self.inner.ensure_size(buffer_size);
+    }
+
     /// Returns immutable references to each channel's audio data.
-    pub fn get_refs(&self) -> Vec<&[f32]> {
-        self.inner.get_refs()
+    pub fn refs(&mut self) -> ChannelRefs<'_, &[f32]> {
+        self.inner.refs()
+    }
+
+    /// Returns immutable references to the frames in `range` of each channel.
+    pub fn refs_range(&mut self, range: Range<usize>) -> ChannelRefs<'_, &[f32]> {
+        self.inner.refs_range(range)
     }
 
     /// Returns the current buffer size.
@@ -218,15 +509,19 @@ impl OutputBuffer {
         self.inner.ensure_size(buffer_size);
     }
 
-    /// Ensures the buffer has the right number of channels based on what JS provides in the outputs array.
+    /// Ensures the buffer has the right ports and channels based on what JS provides in the outputs array.
     pub fn ensure_channels_from_js(&mut self, outputs: &Array) {
-        let mut total_channels = 0;
-        for i in 0..outputs.length() {
-            let channels: Array = outputs.get(i).unchecked_into();
-            total_channels += channels.length() as usize;
-        }
+        self.inner.set_layout_from_js(outputs);
+    }
 
-        self.inner.ensure_channels(total_channels);
+    /// Sets the number of channels in each output port.
+    pub fn set_layout(&mut self, layout: &[usize]) {
+        self.inner.set_layout(layout);
+    }
+
+    /// Returns the number of output channels across all ports.
+    pub fn num_channels(&self) -> usize {
+        self.inner.num_active_channels()
     }
 
     /// Zeros out all output buffers.
@@ -235,62 +530,194 @@ impl OutputBuffer {
     }
 
     /// Returns mutable references to all output channels' audio data.
-    pub fn get_mut_refs(&mut self) -> Vec<&mut [f32]> {
-        self.inner.get_mut_refs()
+    pub fn refs_mut(&mut self) -> ChannelRefs<'_, &mut [f32]> {
+        self.inner.refs_mut()
+    }
+
+    /// Returns mutable references to the frames in `range` of all output channels.
+    pub fn refs_mut_range(&mut self, range: Range<usize>) -> ChannelRefs<'_, &mut [f32]> {
+        self.inner.refs_mut_range(range)
     }
 
     /// Copies all data from Rust storage back to the corresponding JS Float32Arrays.
     pub fn copy_to_js(&self, outputs: &Array) {
         self.inner.copy_to_js(outputs);
     }
+
+    /// Copies all data from Rust storage to the corresponding output slices.
+    pub fn copy_to_slices(&self, outputs: &mut [&mut [f32]]) {
+        self.inner.copy_to_slices(outputs);
+    }
+}
+
+/// Values of a single parameter over one render quantum.
+struct ParameterSlot {
+    name: String,
+    values: Vec<f32>,
+    /// Whether `values` holds a single value for the whole block.
+    constant: bool,
+    /// Smoothing state for parameters declared with [`Smoothing`].
+    smoothing: Option<SmoothedSlot>,
+}
+
+/// Unsmoothed values of a parameter declared with [`Smoothing`].
+struct SmoothedSlot {
+    smoother: Smoother,
+    targets: Vec<f32>,
+    /// Whether `targets` holds a single value for the whole block.
+    constant: bool,
+}
+
+impl ParameterSlot {
+    /// Fills the buffer read from JS or slices: the unsmoothed values if smoothing is used.
+    /// `copy` returns `true` if the value is constant for the block.
+    fn fill(&mut self, copy: impl FnOnce(&mut [f32]) -> bool) {
+        match &mut self.smoothing {
+            Some(smoothed) => smoothed.constant = copy(&mut smoothed.targets),
+            None => self.constant = copy(&mut self.values),
+        }
+    }
 }
 
 /// A buffer that holds parameter values for audio processing.
-/// Each parameter has a buffer of 128 samples (one render quantum).
-/// For a-rate parameters, all 128 values may be different.
-/// For k-rate parameters, all 128 values will be the same.
+/// Each parameter has a buffer of one value per frame of the block.
+/// For a-rate parameters, all values may be different.
+/// For k-rate parameters, all values will be the same.
+///
+/// Slots are allocated once from the processor's parameter descriptors, and only resized when
+/// the block size changes, so filling the buffer does not allocate.
 pub struct ParameterBuffer {
-    /// Storage for parameter buffers. Each parameter gets a Vec<f32> with 128 samples.
-    params: HashMap<String, Vec<f32>>,
-    /// Buffer size (typically 128 for Web Audio API)
-    buffer_size: usize,
+    /// Storage for parameter buffers, in descriptor order.
+    slots: Vec<ParameterSlot>,
+    /// Number of frames in each parameter buffer.
+    block_size: usize,
+    /// Parameter names as JS strings, created with the buffer and reused to look up values.
+    /// Empty when not building for the web, where JS strings can't be created.
+    keys: Vec<JsValue>,
 }
 
 impl ParameterBuffer {
-    /// Creates a new empty `ParameterBuffer`.
-    pub fn new() -> Self {
+    /// Creates a new `ParameterBuffer` with one slot of `block_size` frames per descriptor,
+    /// filled with its default value.
+    pub fn new(descriptors: &[ParameterDescriptor], block_size: usize) -> Self {
+        let slots = descriptors
+            .iter()
+            .map(|desc| ParameterSlot {
+                name: desc.name.clone(),
+                values: vec![desc.default_value; block_size],
+                constant: true,
+                smoothing: (desc.smoothing != Smoothing::None).then(|| SmoothedSlot {
+                    smoother: Smoother::new(desc.smoothing, DEFAULT_SAMPLE_RATE),
+                    targets: vec![desc.default_value; block_size],
+                    constant: true,
+                }),
+            })
+            .collect();
+
+        #[cfg(all(target_family = "wasm", target_os = "unknown"))]
+        let keys = descriptors
+            .iter()
+            .map(|desc| JsValue::from_str(&desc.name))
+            .collect();
+        #[cfg(not(all(target_family = "wasm", target_os = "unknown")))]
+        let keys = Vec::new();
+
         ParameterBuffer {
-            params: HashMap::new(),
-            buffer_size: 128,
+            slots,
+            block_size,
+            keys,
         }
     }
 
+    /// Returns the number of frames in each parameter buffer.
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Resizes the parameter buffers to `block_size` frames, e.g. to match
+    /// [`InputBuffer::buffer_size`]. New frames hold the last value of each parameter.
+    ///
+    /// Only allocates when the block size grows.
+    pub fn set_block_size(&mut self, block_size: usize) {
+        if block_size == self.block_size {
+            return;
+        }
+        for slot in &mut self.slots {
+            resize_holding_last(&mut slot.values, block_size);
+            if let Some(smoothed) = &mut slot.smoothing {
+                resize_holding_last(&mut smoothed.targets, block_size);
+            }
+        }
+        self.block_size = block_size;
+    }
+
+    /// Returns the slot index of the parameter with the given name.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.slots.iter().position(|slot| slot.name == name)
+    }
+
     /// Fills the buffer with parameter values from a JS Object.
-    /// Handles both a-rate (128 values) and k-rate (1 value) parameters.
+    /// Handles both a-rate (one value per frame) and k-rate (1 value) parameters.
     ///
     /// According to Web Audio API spec:
-    /// - If automation rate is "a-rate", array contains 128 values (one per frame)
+    /// - If automation rate is "a-rate", array contains one value per frame
     /// - If no automation, array may contain 1 value that's constant for entire block
-    /// - If automation rate is "k-rate", array contains 1 value for all 128 frames
+    /// - If automation rate is "k-rate", array contains 1 value for all frames
+    ///
+    /// Parameters missing from the object, or given an empty array, keep their previous values.
+    /// Arrays longer than the block are truncated, and shorter ones hold their last value, so
+    /// unexpected lengths never panic.
     pub fn fill_from_js(&mut self, params: &Object) {
-        // Clear existing parameter data
-        for buffer in self.params.values_mut() {
-            buffer.clear();
+        for (slot, key) in self.slots.iter_mut().zip(&self.keys) {
+            if let Ok(value) = Reflect::get(params, key) {
+                if let Some(param_array) = value.dyn_ref::<Float32Array>() {
+                    slot.fill(|buffer| copy_param_from_js(param_array, buffer));
+                }
+            }
         }
+    }
 
-        let keys = Object::keys(params);
-        for i in 0..keys.length() {
-            let key_str = keys.get(i).as_string().unwrap_or_default();
-            if let Ok(value) = Reflect::get(params, &key_str.clone().into()) {
-                if let Some(param_array) = value.dyn_ref::<Float32Array>() {
-                    // Get or create the buffer for this parameter
-                    let buffer = self
-                        .params
-                        .entry(key_str)
-                        .or_insert_with(|| Vec::with_capacity(self.buffer_size));
-
-                    // Copy parameter data from JS
-                    copy_param_from_js(param_array, buffer);
+    /// Sets the values of the parameter at `index` from a slice.
+    /// Follows the same semantics as [`ParameterBuffer::fill_from_js`]: `values` holds either
+    /// 1 value that is constant for the block or one value per frame.
+    pub fn fill_from_slice(&mut self, index: usize, values: &[f32]) {
+        if let Some(slot) = self.slots.get_mut(index) {
+            slot.fill(|buffer| copy_param_from_slice(values, buffer));
+        }
+    }
+
+    /// Sets the sample rate used by parameter smoothing.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        for slot in &mut self.slots {
+            if let Some(smoothed) = &mut slot.smoothing {
+                smoothed.smoother.set_sample_rate(sample_rate);
+            }
+        }
+    }
+
+    /// Applies smoothing to the values of the current block.
+    ///
+    /// Call once per block, after filling the buffer: parameters declared with [`Smoothing`]
+    /// follow the values they were filled with when those are constant for the block, i.e.
+    /// k-rate or unautomated values. They are only constant for the block once the smoother
+    /// has settled. Values that vary within the block come from a-rate automation, which is
+    /// already sample-accurate, so they are used as they are and the smoother continues from
+    /// the last of them.
+    pub fn smooth(&mut self) {
+        for slot in &mut self.slots {
+            if let Some(smoothed) = &mut slot.smoothing {
+                if smoothed.constant {
+                    smoothed
+                        .smoother
+                        .process(&smoothed.targets, &mut slot.values);
+                    // Smoothing is monotonic, so equal ends mean the values did not move
+                    slot.constant = slot.values.first() == slot.values.last();
+                } else {
+                    slot.values.copy_from_slice(&smoothed.targets);
+                    if let Some(&last) = smoothed.targets.last() {
+                        smoothed.smoother.snap(last);
+                    }
+                    slot.constant = false;
                 }
             }
         }
@@ -300,30 +727,38 @@ impl ParameterBuffer {
     /// This is more efficient than cloning and the returned reference
     /// provides access to the full parameter buffers.
     pub fn get_ref(&self) -> ParameterValuesRef<'_> {
-        ParameterValuesRef {
-            params: &self.params,
-        }
+        self.get_ref_range(0..self.block_size)
     }
-}
 
-impl Default for ParameterBuffer {
-    fn default() -> Self {
-        Self::new()
+    /// Returns a reference to the parameter values of the frames in `range`.
+    pub fn get_ref_range(&self, range: Range<usize>) -> ParameterValuesRef<'_> {
+        ParameterValuesRef {
+            slots: &self.slots,
+            start: range.start,
+            end: range.end,
+        }
     }
 }
 
 /// A reference to parameter values without ownership.
-/// Provides access to parameter buffers (128 samples per parameter).
+/// Provides access to parameter buffers (one sample per frame of the block for each parameter).
+#[derive(Clone, Copy)]
 pub struct ParameterValuesRef<'a> {
-    params: &'a HashMap<String, Vec<f32>>,
+    slots: &'a [ParameterSlot],
+    /// Frames of the block covered by this reference.
+    start: usize,
+    end: usize,
 }
 
 impl<'a> ParameterValuesRef<'a> {
-    /// Returns a reference to the parameter buffer (128 samples) with the given name.
+    /// Returns a reference to the parameter buffer (one sample per frame) with the given name.
     /// Returns None if the parameter is not found.
     ///
-    /// Each parameter buffer contains 128 samples:
-    /// - For k-rate parameters: all 128 values are identical
+    /// When a block is split at a scheduled event, the buffer only covers the frames
+    /// of the sub-block being processed.
+    ///
+    /// Each parameter buffer contains one sample per frame of the block:
+    /// - For k-rate parameters: all values are identical
     /// - For a-rate parameters: each value may be different (automation)
     ///
     /// # Example
@@ -336,7 +771,177 @@ impl<'a> ParameterValuesRef<'a> {
     ///     }
     /// }
     /// ```
-    pub fn get(&self, name: &str) -> Option<&[f32]> {
-        self.params.get(name).map(|v| v.as_slice())
+    pub fn get(&self, name: &str) -> Option<&'a [f32]> {
+        self.slots
+            .iter()
+            .find(|slot| slot.name == name)
+            .map(|slot| &slot.values[self.start..self.end])
+    }
+
+    /// Returns a reference to the parameter buffer at `index`, in descriptor order.
+    /// Returns None if there is no such parameter.
+    pub fn get_index(&self, index: usize) -> Option<&'a [f32]> {
+        self.slots
+            .get(index)
+            .map(|slot| &slot.values[self.start..self.end])
+    }
+
+    /// Returns the values of the parameter with the given name, with whether they are constant
+    /// for the block. Returns None if the parameter is not found.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let cutoff = params.value("cutoff").unwrap();
+    /// if let Some(cutoff) = cutoff.constant() {
+    ///     // Compute the coefficients once for the whole block
+    ///     self.filter.set_cutoff(cutoff);
+    /// }
+    /// ```
+    pub fn value(&self, name: &str) -> Option<ParameterValue<'a>> {
+        self.slots
+            .iter()
+            .find(|slot| slot.name == name)
+            .map(|slot| self.slot_value(slot))
+    }
+
+    /// Returns the values of the parameter at `index`, in descriptor order, with whether they
+    /// are constant for the block. Returns None if there is no such parameter.
+    pub fn value_index(&self, index: usize) -> Option<ParameterValue<'a>> {
+        self.slots.get(index).map(|slot| self.slot_value(slot))
+    }
+
+    fn slot_value(&self, slot: &'a ParameterSlot) -> ParameterValue<'a> {
+        let values = &slot.values[self.start..self.end];
+        ParameterValue {
+            values,
+            constant: slot.constant.then(|| values.first().copied()).flatten(),
+        }
+    }
+}
+
+/// The values of a parameter over a block, with whether they are constant.
+///
+/// Dereferences to the per-frame values. Parameters without automation during the block, and
+/// k-rate parameters, are constant: [`ParameterValue::constant`] returns their single value so
+/// processors can skip per-frame work. Fields of a `#[derive(Parameters)]` struct can use this
+/// type instead of `&[f32]`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParameterValue<'a> {
+    values: &'a [f32],
+    constant: Option<f32>,
+}
+
+impl<'a> ParameterValue<'a> {
+    /// Returns the value of each frame of the block.
+    pub fn values(&self) -> &'a [f32] {
+        self.values
+    }
+
+    /// Returns the value of the whole block if it is constant.
+    ///
+    /// Returns `None` if the values may vary, even if a-rate automation happens to produce
+    /// identical values.
+    pub fn constant(&self) -> Option<f32> {
+        self.constant
+    }
+
+    /// Returns `true` if the value is constant for the block.
+    pub fn is_constant(&self) -> bool {
+        self.constant.is_some()
+    }
+}
+
+impl Deref for ParameterValue<'_> {
+    type Target = [f32];
+
+    fn deref(&self) -> &Self::Target {
+        self.values
+    }
+}
+
+impl<'a> From<ParameterValue<'a>> for &'a [f32] {
+    fn from(value: ParameterValue<'a>) -> Self {
+        value.values
+    }
+}
+
+/// Untyped parameters, looked up by name with [`ParameterValuesRef::get`].
+impl Parameters for ParameterValuesRef<'static> {
+    type Values<'a> = ParameterValuesRef<'a>;
+
+    fn descriptors() -> Vec<ParameterDescriptor> {
+        Vec::new()
+    }
+
+    fn values(params: ParameterValuesRef<'_>) -> ParameterValuesRef<'_> {
+        params
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    /// Stands in for a `Float32Array`, counting the subarrays taken from it.
+    struct MockArray {
+        values: Rc<RefCell<Vec<f32>>>,
+        len: usize,
+        prefixes: Rc<Cell<usize>>,
+    }
+
+    impl MockArray {
+        fn new(values: Vec<f32>) -> Self {
+            Self {
+                len: values.len(),
+                values: Rc::new(RefCell::new(values)),
+                prefixes: Rc::new(Cell::new(0)),
+            }
+        }
+    }
+
+    impl FloatArray for MockArray {
+        fn len(&self) -> usize {
+            self.len
+        }
+
+        fn prefix(&self, len: usize) -> Self {
+            self.prefixes.set(self.prefixes.get() + 1);
+            Self {
+                values: self.values.clone(),
+                len,
+                prefixes: self.prefixes.clone(),
+            }
+        }
+
+        // Like `Float32Array`, copies panic unless the lengths are equal
+        fn copy_to(&self, dst: &mut [f32]) {
+            assert_eq!(self.len, dst.len());
+            dst.copy_from_slice(&self.values.borrow()[..self.len]);
+        }
+
+        fn copy_from(&self, src: &[f32]) {
+            assert_eq!(self.len, src.len());
+            self.values.borrow_mut()[..self.len].copy_from_slice(src);
+        }
+    }
+
+    #[test]
+    fn prefixes_are_only_taken_for_mismatched_lengths() {
+        let array = MockArray::new(vec![1.0, 2.0, 3.0, 4.0]);
+
+        let mut block = [0.0; 4];
+        copy_prefix_to(&array, &mut block);
+        copy_prefix_from(&array, &block);
+        assert_eq!(array.prefixes.get(), 0);
+        assert_eq!(block, [1.0, 2.0, 3.0, 4.0]);
+
+        let mut short = [0.0; 2];
+        copy_prefix_to(&array, &mut short);
+        assert_eq!(short, [1.0, 2.0]);
+        copy_prefix_from(&array, &[5.0, 6.0]);
+        assert_eq!(array.prefixes.get(), 2);
+        assert_eq!(*array.values.borrow(), [5.0, 6.0, 3.0, 4.0]);
     }
 }
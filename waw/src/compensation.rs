@@ -0,0 +1,151 @@
+use crate::{
+    buffer::ParameterValuesRef,
+    processor::{ProcessContext, Processor},
+    register,
+};
+
+/// Data of a [`DelayCompensation`] node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DelayCompensationData {
+    /// The initial delay, in frames.
+    pub delay: usize,
+    /// The longest delay the node can be set to, in frames. The delay lines are allocated
+    /// for it up front, so changing the delay never allocates on the audio thread.
+    pub max_delay: usize,
+    /// Number of channels to delay. Further channels are silenced.
+    pub channels: usize,
+}
+
+impl Default for DelayCompensationData {
+    fn default() -> Self {
+        Self {
+            delay: 0,
+            max_delay: 0,
+            channels: 2,
+        }
+    }
+}
+
+/// Delays its input by a number of frames, to align parallel paths with different latencies.
+///
+/// Insert it into the path with less latency, delayed by the difference between the paths'
+/// [`Processor::latency`]. The delay is set by the node's data and can be changed by sending a
+/// new delay as a message, up to the data's `max_delay`. Changing the delay moves the read
+/// position of the delay lines without clearing them. Registered as `"waw-delay-compensation"`.
+///
+/// # Example
+/// ```ignore
+/// let data = DelayCompensationData {
+///     delay: limiter.latency() - dry_path_latency,
+///     max_delay: 4800,
+///     channels: 2,
+/// };
+/// let compensation = DelayCompensation::create_node(ctx, data, None)?;
+/// limiter.set_on_latency_change(move |latency| {
+///     let _ = compensation.send(latency - dry_path_latency);
+/// });
+/// ```
+pub struct DelayCompensation {
+    delay: usize,
+    max_delay: usize,
+    channels: usize,
+    sample_rate: f32,
+    /// Circular delay line for each channel, `max_delay + 1` frames long, allocated in
+    /// `prepare`.
+    lines: Vec<Vec<f32>>,
+    /// Position the next input frame is written to in the delay lines.
+    position: usize,
+}
+
+impl DelayCompensation {
+    /// Returns the current delay, in frames.
+    pub fn delay(&self) -> usize {
+        self.delay
+    }
+
+    /// Returns the longest delay, in frames.
+    pub fn max_delay(&self) -> usize {
+        self.max_delay
+    }
+
+    /// Sets the delay, in frames, clamped to [`DelayCompensation::max_delay`].
+    pub fn set_delay(&mut self, delay: usize) {
+        self.delay = delay.min(self.max_delay);
+    }
+}
+
+impl Processor for DelayCompensation {
+    type Data = DelayCompensationData;
+    type Message = usize;
+
+    fn new(data: Self::Data) -> Self {
+        Self {
+            delay: data.delay,
+            max_delay: data.max_delay.max(data.delay),
+            channels: data.channels,
+            sample_rate: 0.0,
+            lines: Vec::new(),
+            position: 0,
+        }
+    }
+
+    fn prepare(&mut self, sample_rate: f32, _max_block_size: usize) {
+        self.sample_rate = sample_rate;
+        if self.lines.len() != self.channels {
+            self.lines = vec![vec![0.0; self.max_delay + 1]; self.channels];
+        }
+    }
+
+    fn on_message(&mut self, delay: usize) {
+        self.set_delay(delay);
+    }
+
+    fn reset(&mut self) {
+        for line in &mut self.lines {
+            line.fill(0.0);
+        }
+    }
+
+    fn latency(&self) -> usize {
+        self.delay
+    }
+
+    fn tail_time(&self) -> f64 {
+        if self.sample_rate > 0.0 {
+            self.delay as f64 / self.sample_rate as f64
+        } else {
+            0.0
+        }
+    }
+
+    fn process(
+        &mut self,
+        inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        _context: &ProcessContext,
+        _params: &ParameterValuesRef,
+    ) {
+        // Channels without a delay line are silenced rather than left misaligned
+        let len = self.max_delay + 1;
+        for output in outputs.iter_mut().skip(self.lines.len()) {
+            output.fill(0.0);
+        }
+
+        let block_size = outputs.first().map_or(0, |output| output.len());
+        for (channel, (output, line)) in outputs.iter_mut().zip(&mut self.lines).enumerate() {
+            let input = inputs.get(channel).copied().unwrap_or_default();
+            let mut position = self.position;
+            for (i, sample) in output.iter_mut().enumerate() {
+                line[position] = input.get(i).copied().unwrap_or(0.0);
+                *sample = line[(position + len - self.delay) % len];
+                position += 1;
+                if position == len {
+                    position = 0;
+                }
+            }
+        }
+        self.position = (self.position + block_size) % len;
+    }
+}
+
+register!(DelayCompensation, "waw-delay-compensation");
@@ -0,0 +1,305 @@
+use std::fmt::Debug;
+use std::str::FromStr;
+use wasm_bindgen::JsValue;
+
+/// Port and channel configuration of a processor's node, as in `AudioWorkletNodeOptions`.
+///
+/// Returned by [`Processor::node_config`](crate::Processor::node_config). Fields set to `Some`
+/// are declared by the processor: they are applied when the node is created, and options
+/// passed by the caller must agree with them. Fields left as `None` use the caller's options or
+/// the Web Audio defaults.
+///
+/// # Example
+/// ```ignore
+/// // A stereo effect
+/// fn node_config() -> NodeConfig {
+///     NodeConfig {
+///         number_of_inputs: Some(1),
+///         number_of_outputs: Some(1),
+///         output_channel_count: Some(vec![2]),
+///         channel_count: Some(2),
+///         channel_count_mode: Some(ChannelCountMode::Explicit),
+///         ..Default::default()
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeConfig {
+    /// The number of inputs (`numberOfInputs`, defaults to 1).
+    pub number_of_inputs: Option<u32>,
+    /// The number of outputs (`numberOfOutputs`, defaults to 1).
+    pub number_of_outputs: Option<u32>,
+    /// The number of channels of each output (`outputChannelCount`).
+    pub output_channel_count: Option<Vec<u32>>,
+    /// The number of channels used when mixing inputs (`channelCount`, defaults to 2).
+    pub channel_count: Option<u32>,
+    /// How the number of input channels is computed (`channelCountMode`, defaults to `max`).
+    pub channel_count_mode: Option<ChannelCountMode>,
+    /// How input channels are up- or down-mixed (`channelInterpretation`, defaults to `speakers`).
+    pub channel_interpretation: Option<ChannelInterpretation>,
+    /// The number of frames in each block the node renders (`renderQuantumSize`, a waw option).
+    ///
+    /// Defaults to the context's `renderQuantumSize`, or 128 on browsers without it. Set it when
+    /// the context was created with a `renderSizeHint`, so buffers are allocated for the right size
+    /// up front. Blocks of another size are still rendered, with buffers resized on the fly.
+    pub render_quantum_size: Option<usize>,
+}
+
+impl NodeConfig {
+    /// Merges options requested by the caller into the declared configuration.
+    ///
+    /// Returns an error if a requested field conflicts with a declared one, or if the merged
+    /// configuration is invalid, e.g. `output_channel_count` does not list every output.
+    pub fn merge(&self, requested: &NodeConfig) -> Result<NodeConfig, String> {
+        let merged = NodeConfig {
+            number_of_inputs: merge_field(
+                "numberOfInputs",
+                &self.number_of_inputs,
+                &requested.number_of_inputs,
+            )?,
+            number_of_outputs: merge_field(
+                "numberOfOutputs",
+                &self.number_of_outputs,
+                &requested.number_of_outputs,
+            )?,
+            output_channel_count: merge_field(
+                "outputChannelCount",
+                &self.output_channel_count,
+                &requested.output_channel_count,
+            )?,
+            channel_count: merge_field(
+                "channelCount",
+                &self.channel_count,
+                &requested.channel_count,
+            )?,
+            channel_count_mode: merge_field(
+                "channelCountMode",
+                &self.channel_count_mode,
+                &requested.channel_count_mode,
+            )?,
+            channel_interpretation: merge_field(
+                "channelInterpretation",
+                &self.channel_interpretation,
+                &requested.channel_interpretation,
+            )?,
+            render_quantum_size: merge_field(
+                "renderQuantumSize",
+                &self.render_quantum_size,
+                &requested.render_quantum_size,
+            )?,
+        };
+        merged.validate()?;
+        Ok(merged)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.channel_count == Some(0) {
+            return Err("`channelCount` must be at least 1".to_string());
+        }
+        if self.render_quantum_size == Some(0) {
+            return Err("`renderQuantumSize` must be at least 1".to_string());
+        }
+        if let Some(counts) = &self.output_channel_count {
+            let number_of_outputs = self.number_of_outputs.unwrap_or(1);
+            if counts.len() != number_of_outputs as usize {
+                return Err(format!(
+                    "`outputChannelCount` lists {} outputs but the node has {number_of_outputs}",
+                    counts.len()
+                ));
+            }
+            if counts.contains(&0) {
+                return Err("`outputChannelCount` must be at least 1 for every output".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of channels of each input, assuming every input is mixed to
+    /// `channel_count` channels.
+    pub fn input_layout(&self) -> Vec<usize> {
+        let number_of_inputs = self.number_of_inputs.unwrap_or(1) as usize;
+        vec![self.channel_count.unwrap_or(2) as usize; number_of_inputs]
+    }
+
+    /// Returns the number of channels of each output.
+    ///
+    /// Without `output_channel_count`, a node with one input and one output follows the channel
+    /// count of its input, and other nodes have mono outputs, as in Web Audio.
+    pub fn output_layout(&self) -> Vec<usize> {
+        if let Some(counts) = &self.output_channel_count {
+            return counts.iter().map(|&count| count as usize).collect();
+        }
+
+        let number_of_inputs = self.number_of_inputs.unwrap_or(1);
+        let number_of_outputs = self.number_of_outputs.unwrap_or(1) as usize;
+        if number_of_inputs == 1 && number_of_outputs == 1 {
+            vec![self.channel_count.unwrap_or(2) as usize]
+        } else {
+            vec![1; number_of_outputs]
+        }
+    }
+}
+
+fn merge_field<T: Clone + PartialEq + Debug>(
+    name: &str,
+    declared: &Option<T>,
+    requested: &Option<T>,
+) -> Result<Option<T>, String> {
+    match (declared, requested) {
+        (Some(declared), Some(requested)) if declared != requested => Err(format!(
+            "`{name}` is declared as {declared:?} by the processor but {requested:?} was requested"
+        )),
+        _ => Ok(declared.clone().or_else(|| requested.clone())),
+    }
+}
+
+/// How the number of channels of a node's inputs is computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelCountMode {
+    /// The maximum number of channels of all connections to the input.
+    Max,
+    /// Like `Max`, but limited to `channelCount`.
+    ClampedMax,
+    /// Exactly `channelCount`.
+    Explicit,
+}
+
+impl From<ChannelCountMode> for JsValue {
+    fn from(val: ChannelCountMode) -> Self {
+        match val {
+            ChannelCountMode::Max => "max".into(),
+            ChannelCountMode::ClampedMax => "clamped-max".into(),
+            ChannelCountMode::Explicit => "explicit".into(),
+        }
+    }
+}
+
+impl FromStr for ChannelCountMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "max" => Ok(ChannelCountMode::Max),
+            "clamped-max" => Ok(ChannelCountMode::ClampedMax),
+            "explicit" => Ok(ChannelCountMode::Explicit),
+            other => Err(format!("Unknown channel count mode `{other}`")),
+        }
+    }
+}
+
+/// How input channels are up- or down-mixed to the input's channel count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelInterpretation {
+    /// Mix using speaker layouts (mono, stereo, quad, 5.1).
+    Speakers,
+    /// Fill or drop channels in order.
+    Discrete,
+}
+
+impl From<ChannelInterpretation> for JsValue {
+    fn from(val: ChannelInterpretation) -> Self {
+        match val {
+            ChannelInterpretation::Speakers => "speakers".into(),
+            ChannelInterpretation::Discrete => "discrete".into(),
+        }
+    }
+}
+
+impl FromStr for ChannelInterpretation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "speakers" => Ok(ChannelInterpretation::Speakers),
+            "discrete" => Ok(ChannelInterpretation::Discrete),
+            other => Err(format!("Unknown channel interpretation `{other}`")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stereo_effect() -> NodeConfig {
+        NodeConfig {
+            number_of_inputs: Some(1),
+            number_of_outputs: Some(1),
+            output_channel_count: Some(vec![2]),
+            channel_count: Some(2),
+            channel_count_mode: Some(ChannelCountMode::Explicit),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merge_applies_declared_and_requested_fields() {
+        let requested = NodeConfig {
+            channel_interpretation: Some(ChannelInterpretation::Discrete),
+            output_channel_count: Some(vec![2]),
+            ..Default::default()
+        };
+
+        let merged = stereo_effect().merge(&requested).unwrap();
+
+        assert_eq!(merged.output_channel_count, Some(vec![2]));
+        assert_eq!(
+            merged.channel_interpretation,
+            Some(ChannelInterpretation::Discrete)
+        );
+    }
+
+    #[test]
+    fn merge_rejects_conflicting_overrides() {
+        let requested = NodeConfig {
+            output_channel_count: Some(vec![1]),
+            ..Default::default()
+        };
+
+        let error = stereo_effect().merge(&requested).unwrap_err();
+
+        assert!(error.contains("outputChannelCount"), "{error}");
+    }
+
+    #[test]
+    fn merge_rejects_mismatched_output_channel_count() {
+        let requested = NodeConfig {
+            number_of_outputs: Some(2),
+            output_channel_count: Some(vec![2]),
+            ..Default::default()
+        };
+
+        assert!(NodeConfig::default().merge(&requested).is_err());
+    }
+
+    #[test]
+    fn merge_applies_requested_render_quantum_size() {
+        let requested = NodeConfig {
+            render_quantum_size: Some(256),
+            ..Default::default()
+        };
+        let merged = stereo_effect().merge(&requested).unwrap();
+        assert_eq!(merged.render_quantum_size, Some(256));
+
+        let empty = NodeConfig {
+            render_quantum_size: Some(0),
+            ..Default::default()
+        };
+        assert!(NodeConfig::default().merge(&empty).is_err());
+    }
+
+    #[test]
+    fn layouts_follow_web_audio_defaults() {
+        assert_eq!(NodeConfig::default().input_layout(), [2]);
+        assert_eq!(NodeConfig::default().output_layout(), [2]);
+
+        let generator = NodeConfig {
+            number_of_inputs: Some(0),
+            number_of_outputs: Some(2),
+            ..Default::default()
+        };
+        assert!(generator.input_layout().is_empty());
+        assert_eq!(generator.output_layout(), [1, 1]);
+        assert_eq!(stereo_effect().output_layout(), [2]);
+    }
+}
@@ -0,0 +1,139 @@
+// Panics are only caught by the worklet wrapper; the rest is tested natively
+#![cfg_attr(
+    not(all(target_family = "wasm", target_os = "unknown")),
+    allow(dead_code)
+)]
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe, PanicHookInfo};
+use std::sync::{Arc, Mutex, Once};
+use wasm_bindgen::JsValue;
+
+/// An error that stopped a processor.
+///
+/// A panicking processor is stopped on its own: its node outputs silence from then on and the
+/// error is reported on the node, while other processors on the context keep running. This
+/// relies on the worklet being built with `panic=unwind`, as in this repository's
+/// `.cargo/config.toml`; with `panic=abort`, a panic traps the WebAssembly instance shared by
+/// every processor on the context, and the context has to be recreated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcessorError {
+    /// The processor panicked while handling a message or rendering a block.
+    Panicked {
+        /// The panic message.
+        message: String,
+    },
+    /// The browser reported a `processorerror` without a caught Rust panic, e.g. a wasm trap.
+    Aborted,
+}
+
+impl fmt::Display for ProcessorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessorError::Panicked { message } => write!(f, "Processor panicked: {message}"),
+            ProcessorError::Aborted => write!(f, "Processor aborted"),
+        }
+    }
+}
+
+impl std::error::Error for ProcessorError {}
+
+impl From<ProcessorError> for JsValue {
+    fn from(error: ProcessorError) -> Self {
+        js_sys::Error::new(&error.to_string()).into()
+    }
+}
+
+/// The error of a processor, shared between the processor wrapper and its node.
+#[derive(Default)]
+pub(crate) struct ErrorSlot {
+    error: Mutex<Option<ProcessorError>>,
+}
+
+impl ErrorSlot {
+    /// Records an error, keeping the first one if the processor already failed.
+    pub(crate) fn set(&self, error: ProcessorError) {
+        let mut slot = self.error.lock().unwrap_or_else(|e| e.into_inner());
+        slot.get_or_insert(error);
+    }
+
+    /// Returns the recorded error, if any.
+    pub(crate) fn get(&self) -> Option<ProcessorError> {
+        let slot = self.error.lock().unwrap_or_else(|e| e.into_inner());
+        slot.clone()
+    }
+}
+
+thread_local! {
+    /// Error slot of the processor currently running on this thread.
+    static CURRENT: RefCell<Option<Arc<ErrorSlot>>> = const { RefCell::new(None) };
+}
+
+/// Runs `f` on behalf of the processor owning `slot`, recording any panic in the slot.
+///
+/// Returns `None` if `f` panicked. The panic hook records the message, so it is kept even when
+/// the panic can't be caught: with `panic=abort` the worklet traps instead of returning, and
+/// the node picks the error up from its `processorerror` event.
+pub(crate) fn catch_panics<R>(slot: &Arc<ErrorSlot>, f: impl FnOnce() -> R) -> Option<R> {
+    static INSTALL_HOOK: Once = Once::new();
+    INSTALL_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            record_panic(info);
+            previous(info);
+        }));
+    });
+
+    CURRENT.with(|current| current.replace(Some(slot.clone())));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CURRENT.with(|current| current.take());
+    result.ok()
+}
+
+fn record_panic(info: &PanicHookInfo<'_>) {
+    // `try_with` as the hook may run while the thread is shutting down.
+    let _ = CURRENT.try_with(|current| {
+        if let Some(slot) = &*current.borrow() {
+            slot.set(ProcessorError::Panicked {
+                message: payload_message(info.payload()),
+            });
+        }
+    });
+}
+
+fn payload_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panics_are_recorded_for_their_processor() {
+        let failing = Arc::new(ErrorSlot::default());
+        let running = Arc::new(ErrorSlot::default());
+
+        assert_eq!(
+            catch_panics(&failing, || panic!("out of range")),
+            None::<()>
+        );
+        assert_eq!(catch_panics(&running, || 1), Some(1));
+
+        assert_eq!(
+            failing.get(),
+            Some(ProcessorError::Panicked {
+                message: "out of range".to_string()
+            })
+        );
+        assert_eq!(running.get(), None);
+    }
+}
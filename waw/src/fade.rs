@@ -0,0 +1,132 @@
+use crate::buffer::{InputBuffer, OutputBuffer};
+
+/// Default length of the fades applied by the node wrapper, in frames.
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+pub(crate) const DEFAULT_FADE_FRAMES: usize = 128;
+
+/// A linear gain ramp, advanced one frame at a time.
+#[derive(Clone, Copy)]
+struct Ramp {
+    value: f32,
+    target: f32,
+    step: f32,
+}
+
+impl Ramp {
+    fn new(value: f32) -> Self {
+        Self {
+            value,
+            target: value,
+            step: 0.0,
+        }
+    }
+
+    /// Ramps from the current value to `target` over `frames` frames.
+    fn set_target(&mut self, target: f32, frames: usize) {
+        self.target = target;
+        if frames == 0 {
+            self.value = target;
+            self.step = 0.0;
+        } else {
+            self.step = (target - self.value) / frames as f32;
+        }
+    }
+
+    fn is_settled(&self) -> bool {
+        self.value == self.target
+    }
+
+    /// Returns the gain for the current frame and moves to the next one.
+    fn next(&mut self) -> f32 {
+        let value = self.value;
+        self.advance(1);
+        value
+    }
+
+    fn advance(&mut self, frames: usize) {
+        if self.is_settled() {
+            return;
+        }
+        let value = self.value + self.step * frames as f32;
+        let overshot = if self.step > 0.0 {
+            value >= self.target
+        } else {
+            value <= self.target
+        };
+        self.value = if overshot { self.target } else { value };
+    }
+}
+
+/// Fades applied around a processor's output by the node wrapper, so processors don't have to
+/// declick themselves.
+///
+/// The output fades in when the processor starts rendering, and crossfades to the dry input
+/// while the node is bypassed. Output port `n` is bypassed to input port `n`, channel by channel;
+/// output channels without a matching input channel are silent.
+pub(crate) struct Declick {
+    /// Gain of the processor's output.
+    wet: Ramp,
+    /// Gain of the dry input.
+    dry: Ramp,
+    fade_frames: usize,
+}
+
+impl Declick {
+    /// Creates fades of `fade_frames` frames, starting with a fade-in.
+    pub(crate) fn new(fade_frames: usize) -> Self {
+        let mut wet = Ramp::new(0.0);
+        wet.set_target(1.0, fade_frames);
+        Self {
+            wet,
+            dry: Ramp::new(0.0),
+            fade_frames,
+        }
+    }
+
+    /// Returns the length of the fades, in frames.
+    pub(crate) fn fade_frames(&self) -> usize {
+        self.fade_frames
+    }
+
+    /// Sets the length of the fades, including any fade in progress.
+    pub(crate) fn set_fade_frames(&mut self, fade_frames: usize) {
+        self.fade_frames = fade_frames;
+        self.wet.set_target(self.wet.target, fade_frames);
+        self.dry.set_target(self.dry.target, fade_frames);
+    }
+
+    /// Crossfades to the dry input if `bypassed`, or back to the processor's output.
+    pub(crate) fn set_bypass(&mut self, bypassed: bool) {
+        let (wet, dry) = if bypassed { (0.0, 1.0) } else { (1.0, 0.0) };
+        self.wet.set_target(wet, self.fade_frames);
+        self.dry.set_target(dry, self.fade_frames);
+    }
+
+    /// Applies the fades to a rendered block.
+    pub(crate) fn apply(&mut self, inputs: &mut InputBuffer, outputs: &mut OutputBuffer) {
+        let block_size = inputs.buffer_size();
+        if self.wet.is_settled() && self.dry.is_settled() && self.wet.value == 1.0 {
+            return;
+        }
+
+        let mut input_refs = inputs.refs();
+        let mut output_refs = outputs.refs_mut();
+        let input_ports = input_refs.ports();
+        let mut output_ports = output_refs.ports();
+
+        for (index, output_port) in output_ports.iter_mut().enumerate() {
+            let input_port = input_ports.port(index).unwrap_or_default();
+            for (channel, output) in output_port.iter_mut().enumerate() {
+                let input = input_port.get(channel).copied().unwrap_or_default();
+                let (mut wet, mut dry) = (self.wet, self.dry);
+                for (i, sample) in output.iter_mut().enumerate() {
+                    let dry_sample = input.get(i).copied().unwrap_or(0.0);
+                    *sample = *sample * wet.next() + dry_sample * dry.next();
+                }
+            }
+        }
+
+        self.wet.advance(block_size);
+        self.dry.advance(block_size);
+    }
+}
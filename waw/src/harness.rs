@@ -0,0 +1,891 @@
+use crate::{
+    buffer::{InputBuffer, OutputBuffer, ParameterBuffer, RENDER_QUANTUM_SIZE},
+    fade::Declick,
+    message::{self, command_channel, event_channel, Command, EventReceiver, EventSender},
+    parameter::{validate_descriptors, AutomationRate, ParameterDescriptor},
+    processor::{ProcessContext, Processor},
+    ring::{Consumer, Producer},
+    schedule::{self, EventQueue, Timestamp},
+    tail::Release,
+};
+
+/// Renders a [`Processor`] without a browser.
+///
+/// The harness drives the processor through the same buffers as the worklet wrapper, one
+/// render quantum at a time, so DSP code can be tested with `cargo test` on the host. Render
+/// quanta are 128 frames unless changed with [`RenderHarness::set_block_size`].
+///
+/// # Example
+///
+/// ```ignore
+/// let mut harness = RenderHarness::<FilterProcessor>::new(data, 48000.0, 1);
+/// let input = vec![1.0; 512];
+/// let outputs = harness.render(512, &[&input], &[("cutoff", &[200.0])]);
+/// assert_eq!(outputs[0].len(), 512);
+/// ```
+pub struct RenderHarness<P: Processor> {
+    processor: P,
+    sample_rate: f32,
+    /// Frame position of the next block to render.
+    current_frame: u64,
+    descriptors: Vec<ParameterDescriptor>,
+    /// Number of channels in each input port, or `None` to treat all inputs as a single port.
+    input_ports: Option<Vec<usize>>,
+    input_buffer: InputBuffer,
+    output_buffer: OutputBuffer,
+    parameter_buffer: ParameterBuffer,
+    /// Commands sent to the processor, delivered before the next block as by the node wrapper.
+    commands: Producer<Command<P::Message>>,
+    messages: Consumer<Command<P::Message>>,
+    /// Scheduled messages waiting for their frame.
+    queue: EventQueue<P::Message>,
+    /// Events posted by the processor, waiting to be received.
+    events: EventSender<P::Event>,
+    event_receiver: EventReceiver<P::Event>,
+    /// Bypass applied to the processor's output.
+    declick: Declick,
+    /// Tail of the processor, once it has been deactivated.
+    release: Option<Release>,
+    /// Set once the processor has stopped after being deactivated.
+    stopped: bool,
+}
+
+impl<P: Processor> RenderHarness<P> {
+    /// Creates a new harness for a processor built from `data`, with a single output port.
+    ///
+    /// All input channels given to the harness are treated as a single input port.
+    /// Every parameter declared by [`Processor::parameter_descriptors`] starts at its default value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the parameter descriptors would be rejected at registration, see
+    /// [`validate_descriptors`](crate::validate_descriptors).
+    pub fn new(data: P::Data, sample_rate: f32, output_channels: usize) -> Self {
+        Self::build(data, sample_rate, None, &[output_channels])
+    }
+
+    /// Creates a new harness for a processor with several input or output ports.
+    ///
+    /// `input_ports` and `output_ports` hold the number of channels in each port. Input channels
+    /// given to the harness are assigned to ports in order, with missing channels left silent.
+    pub fn with_ports(
+        data: P::Data,
+        sample_rate: f32,
+        input_ports: &[usize],
+        output_ports: &[usize],
+    ) -> Self {
+        Self::build(data, sample_rate, Some(input_ports.to_vec()), output_ports)
+    }
+
+    /// Creates a new harness with the ports and render quantum size declared by
+    /// [`Processor::node_config`].
+    ///
+    /// Undeclared fields follow the Web Audio defaults, see
+    /// [`NodeConfig::input_layout`](crate::NodeConfig::input_layout) and
+    /// [`NodeConfig::output_layout`](crate::NodeConfig::output_layout).
+    pub fn from_node_config(data: P::Data, sample_rate: f32) -> Self {
+        let config = P::node_config();
+        let mut harness = Self::with_ports(
+            data,
+            sample_rate,
+            &config.input_layout(),
+            &config.output_layout(),
+        );
+        if let Some(block_size) = config.render_quantum_size {
+            harness.set_block_size(block_size);
+        }
+        harness
+    }
+
+    fn build(
+        data: P::Data,
+        sample_rate: f32,
+        input_ports: Option<Vec<usize>>,
+        output_ports: &[usize],
+    ) -> Self {
+        let descriptors = P::parameter_descriptors();
+        if let Err(e) = validate_descriptors(&descriptors) {
+            panic!("Invalid parameter descriptors: {e}");
+        }
+        let mut parameter_buffer = ParameterBuffer::new(&descriptors, RENDER_QUANTUM_SIZE);
+        parameter_buffer.set_sample_rate(sample_rate);
+
+        let mut output_buffer = OutputBuffer::new(0, RENDER_QUANTUM_SIZE);
+        output_buffer.set_layout(output_ports);
+
+        let mut processor = P::new(data);
+        processor.prepare(sample_rate, RENDER_QUANTUM_SIZE);
+
+        let (commands, messages) = command_channel();
+        let (events, event_receiver) = event_channel();
+
+        Self {
+            processor,
+            sample_rate,
+            current_frame: 0,
+            descriptors,
+            input_ports,
+            input_buffer: InputBuffer::new(0, RENDER_QUANTUM_SIZE),
+            output_buffer,
+            parameter_buffer,
+            commands,
+            messages,
+            queue: EventQueue::new(),
+            events,
+            event_receiver,
+            declick: Declick::new(0),
+            release: None,
+            stopped: false,
+        }
+    }
+
+    /// Returns the number of frames in each block.
+    pub fn block_size(&self) -> usize {
+        self.input_buffer.buffer_size()
+    }
+
+    /// Renders blocks of `block_size` frames from now on, as a context created with a
+    /// `renderSizeHint` would.
+    ///
+    /// Calls [`Processor::prepare`] again with the new size.
+    pub fn set_block_size(&mut self, block_size: usize) {
+        self.input_buffer.ensure_size(block_size);
+        self.output_buffer.ensure_size(block_size);
+        self.parameter_buffer.set_block_size(block_size);
+        self.processor.prepare(self.sample_rate, block_size);
+    }
+
+    /// Returns a reference to the processor under test.
+    pub fn processor(&self) -> &P {
+        &self.processor
+    }
+
+    /// Returns a mutable reference to the processor under test.
+    pub fn processor_mut(&mut self) -> &mut P {
+        &mut self.processor
+    }
+
+    /// Returns the frame position of the next block to render.
+    pub fn current_frame(&self) -> u64 {
+        self.current_frame
+    }
+
+    /// Sends a message to the processor, as `AudioWorkletNodeWrapper::send` would.
+    ///
+    /// The message is delivered to [`Processor::on_message`] before the next block is rendered.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the processor's message queue is full, as sending would fail on a node.
+    pub fn send(&mut self, message: P::Message) {
+        self.post(Command::Message { message, at: None });
+    }
+
+    /// Schedules a message, as `AudioWorkletNodeWrapper::schedule` would.
+    ///
+    /// The message is delivered to [`Processor::handle_event`] before the frame at `at` is
+    /// rendered, splitting the block it falls in.
+    pub fn schedule(&mut self, message: P::Message, at: Timestamp) {
+        self.post(Command::Message {
+            message,
+            at: Some(at),
+        });
+    }
+
+    /// Resets the processor, as `AudioWorkletNodeWrapper::reset` would.
+    ///
+    /// Before the next block, drops scheduled messages that have not been delivered and calls
+    /// [`Processor::reset`].
+    pub fn reset(&mut self) {
+        self.post(Command::Reset);
+    }
+
+    /// Bypasses the processor, as `AudioWorkletNodeWrapper::set_bypass` would.
+    pub fn set_bypass(&mut self, bypassed: bool) {
+        self.post(Command::Bypass(bypassed));
+    }
+
+    /// Sets the length of fades, as `AudioWorkletNodeWrapper::set_fade_length` would.
+    ///
+    /// Unlike the node wrapper, the harness doesn't fade by default, so the processor's output is
+    /// rendered exactly and processors are not faded in.
+    pub fn set_fade_length(&mut self, frames: usize) {
+        self.post(Command::FadeLength(frames));
+    }
+
+    fn post(&mut self, command: Command<P::Message>) {
+        if self.commands.push(command).is_err() {
+            panic!("Message queue is full: render a block to deliver waiting messages");
+        }
+    }
+
+    /// Deactivates the processor, as dropping the `AudioWorkletNodeWrapper` would.
+    ///
+    /// The processor keeps rendering until its tail has finished and its output has faded out,
+    /// then [`Processor::on_deactivate`] is called and the harness renders silence.
+    pub fn deactivate(&mut self) {
+        if self.release.is_none() {
+            self.release = Some(Release::new());
+        }
+    }
+
+    /// Returns `true` once the processor has stopped after being deactivated.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Receives the next event posted by the processor, if any, as
+    /// `AudioWorkletNodeWrapper::try_recv` would.
+    pub fn try_recv(&self) -> Option<P::Event> {
+        self.event_receiver.try_recv()
+    }
+
+    /// Returns the events posted by the processor that have not been received yet.
+    pub fn take_events(&mut self) -> Vec<P::Event> {
+        self.event_receiver.acknowledge();
+        std::iter::from_fn(|| self.event_receiver.try_recv()).collect()
+    }
+
+    /// Sets the values of a parameter for the next processed block.
+    ///
+    /// `values` holds either 1 value that is constant for the block or one value per frame,
+    /// as the browser would provide them. Other lengths are handled as in
+    /// [`ParameterBuffer::fill_from_js`]: extra values are ignored, and the last value is held
+    /// until the end of the block.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the processor does not declare a parameter called `name`.
+    pub fn set_parameter(&mut self, name: &str, values: &[f32]) {
+        let index = self.parameter_index(name);
+        self.parameter_buffer.fill_from_slice(index, values);
+    }
+
+    /// Processes a single render quantum of [`RenderHarness::block_size`] frames.
+    ///
+    /// `inputs` holds one slice per input channel; channels shorter than the block are
+    /// zero-padded. Each output channel is copied into the matching slice of `outputs`,
+    /// with the channels of all output ports flattened in order.
+    pub fn process_block(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]]) {
+        if self.stopped {
+            outputs.iter_mut().for_each(|output| output.fill(0.0));
+            return;
+        }
+
+        message::deliver_commands(
+            &mut self.messages,
+            &mut self.processor,
+            &mut self.queue,
+            &mut self.declick,
+            self.sample_rate,
+        );
+
+        match &self.input_ports {
+            Some(layout) => self.input_buffer.fill_from_slices(inputs, layout),
+            None => self.input_buffer.fill_from_slices(inputs, &[inputs.len()]),
+        }
+
+        self.output_buffer
+            .ensure_size(self.input_buffer.buffer_size());
+        self.output_buffer.clear();
+
+        self.parameter_buffer
+            .set_block_size(self.input_buffer.buffer_size());
+        self.parameter_buffer.smooth();
+
+        let block_size = self.input_buffer.buffer_size();
+        let context = ProcessContext::new(self.sample_rate, self.current_frame, block_size);
+
+        schedule::render_block(
+            &mut self.processor,
+            &mut self.queue,
+            &mut self.input_buffer,
+            &mut self.output_buffer,
+            &self.parameter_buffer,
+            &context,
+        );
+        self.current_frame += block_size as u64;
+
+        self.declick
+            .apply(&mut self.input_buffer, &mut self.output_buffer);
+
+        if let Some(release) = &mut self.release {
+            self.stopped = release.end_block(
+                &self.processor,
+                &mut self.input_buffer,
+                &mut self.output_buffer,
+                self.sample_rate,
+                self.declick.fade_frames(),
+            );
+        }
+
+        self.output_buffer.copy_to_slices(outputs);
+
+        while let Some(event) = self.processor.poll_event() {
+            self.events.send(event);
+        }
+
+        if self.stopped {
+            self.processor.on_deactivate();
+        }
+    }
+
+    /// Renders `num_frames` frames, splitting them into render quanta.
+    ///
+    /// `inputs` holds one slice per input channel and is zero-padded past its end.
+    /// `automation` holds one value per frame for each listed parameter, holding its last value
+    /// past its end. a-rate parameters receive every frame of the block, or a single value when it
+    /// is constant for the block as the browser does without automation, while k-rate parameters
+    /// receive the value at the start of each block. Parameters not listed keep their current value.
+    ///
+    /// Returns one buffer of `num_frames` samples per output channel,
+    /// with the channels of all output ports flattened in order.
+    pub fn render(
+        &mut self,
+        num_frames: usize,
+        inputs: &[&[f32]],
+        automation: &[(&str, &[f32])],
+    ) -> Vec<Vec<f32>> {
+        let block_size = self.block_size();
+        let num_channels = self.output_buffer.num_channels();
+        let mut outputs = vec![Vec::with_capacity(num_frames); num_channels];
+        let mut block_outputs = vec![vec![0.0; block_size]; num_channels];
+        let mut block = vec![0.0; block_size];
+
+        for start in (0..num_frames).step_by(block_size) {
+            let end = (start + block_size).min(num_frames);
+
+            for (name, curve) in automation {
+                if curve.is_empty() {
+                    continue;
+                }
+
+                for (i, value) in block.iter_mut().enumerate() {
+                    *value = curve[(start + i).min(curve.len() - 1)];
+                }
+
+                let index = self.parameter_index(name);
+                let constant = block.iter().all(|&value| value == block[0]);
+                match self.descriptors[index].automation_rate {
+                    AutomationRate::ARate if !constant => self.set_parameter(name, &block),
+                    _ => self.set_parameter(name, &block[..1]),
+                }
+            }
+
+            let block_inputs: Vec<&[f32]> = inputs
+                .iter()
+                .map(|channel| &channel[start.min(channel.len())..end.min(channel.len())])
+                .collect();
+            let mut block_refs: Vec<&mut [f32]> = block_outputs
+                .iter_mut()
+                .map(|channel| channel.as_mut_slice())
+                .collect();
+
+            self.process_block(&block_inputs, &mut block_refs);
+
+            for (output, block) in outputs.iter_mut().zip(&block_outputs) {
+                output.extend_from_slice(&block[..end - start]);
+            }
+        }
+
+        outputs
+    }
+
+    fn parameter_index(&self, name: &str) -> usize {
+        self.parameter_buffer
+            .index_of(name)
+            .unwrap_or_else(|| panic!("Processor has no parameter named `{name}`"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{buffer::ParameterValuesRef, port::Ports, schedule::QUEUE_CAPACITY};
+
+    /// Multiplies each input by an a-rate `gain` and adds a k-rate `offset`.
+    struct GainProcessor;
+
+    impl Processor for GainProcessor {
+        type Data = ();
+
+        fn new(_data: Self::Data) -> Self {
+            Self
+        }
+
+        fn process(
+            &mut self,
+            inputs: &[&[f32]],
+            outputs: &mut [&mut [f32]],
+            _context: &ProcessContext,
+            params: &ParameterValuesRef,
+        ) {
+            let gain = params.get("gain").unwrap();
+            let offset = params.get("offset").unwrap();
+            for (channel, output) in outputs.iter_mut().enumerate() {
+                let input = inputs.get(channel);
+                for (i, sample) in output.iter_mut().enumerate() {
+                    let x = input.map_or(0.0, |input| input[i]);
+                    *sample = x * gain[i] + offset[i];
+                }
+            }
+        }
+
+        fn parameter_descriptors() -> Vec<ParameterDescriptor> {
+            vec![
+                ParameterDescriptor {
+                    name: "gain".to_string(),
+                    default_value: 1.0,
+                    min_value: 0.0,
+                    max_value: 1000.0,
+                    automation_rate: AutomationRate::ARate,
+                    ..Default::default()
+                },
+                ParameterDescriptor {
+                    name: "offset".to_string(),
+                    default_value: 0.0,
+                    min_value: -1000.0,
+                    max_value: 1000.0,
+                    automation_rate: AutomationRate::KRate,
+                    ..Default::default()
+                },
+            ]
+        }
+    }
+
+    /// Multiplies the signal on input 0 by the sidechain on input 1, one output per input.
+    struct SidechainProcessor;
+
+    impl Processor for SidechainProcessor {
+        type Data = ();
+
+        fn new(_data: Self::Data) -> Self {
+            Self
+        }
+
+        fn process_ports(
+            &mut self,
+            inputs: &Ports<&[f32]>,
+            outputs: &mut Ports<&mut [f32]>,
+            _context: &ProcessContext,
+            _params: &ParameterValuesRef,
+        ) {
+            let signal = inputs.port(0).unwrap();
+            let sidechain = inputs.port(1).unwrap();
+            let mut ports = outputs.iter_mut();
+            let (product, key) = (ports.next().unwrap(), ports.next().unwrap());
+
+            for (i, sample) in product[0].iter_mut().enumerate() {
+                *sample = signal.first().map_or(0.0, |channel| channel[i]) * sidechain[0][i];
+            }
+            key[0].copy_from_slice(sidechain[0]);
+        }
+    }
+
+    /// Implements neither `process` nor `process_ports`.
+    struct SilentProcessor;
+
+    impl Processor for SilentProcessor {
+        type Data = ();
+
+        fn new(_data: Self::Data) -> Self {
+            Self
+        }
+    }
+
+    /// Outputs a constant level set by messages, posting the number of rendered blocks.
+    struct LevelProcessor {
+        level: f32,
+        blocks: usize,
+        posted: usize,
+    }
+
+    impl Processor for LevelProcessor {
+        type Data = f32;
+        type Message = f32;
+        type Event = usize;
+
+        fn new(level: Self::Data) -> Self {
+            Self {
+                level,
+                blocks: 0,
+                posted: 0,
+            }
+        }
+
+        fn on_message(&mut self, level: f32) {
+            self.level = level;
+        }
+
+        fn fade_out_time(&self) -> Option<f64> {
+            Some(64.0 / 48000.0)
+        }
+
+        fn poll_event(&mut self) -> Option<usize> {
+            (self.posted < self.blocks).then(|| {
+                self.posted = self.blocks;
+                self.blocks
+            })
+        }
+
+        fn process(
+            &mut self,
+            _inputs: &[&[f32]],
+            outputs: &mut [&mut [f32]],
+            _context: &ProcessContext,
+            _params: &ParameterValuesRef,
+        ) {
+            outputs[0].fill(self.level);
+            self.blocks += 1;
+        }
+    }
+
+    /// Outputs the time of each frame.
+    struct ClockProcessor;
+
+    impl Processor for ClockProcessor {
+        type Data = ();
+
+        fn new(_data: Self::Data) -> Self {
+            Self
+        }
+
+        fn process(
+            &mut self,
+            _inputs: &[&[f32]],
+            outputs: &mut [&mut [f32]],
+            context: &ProcessContext,
+            _params: &ParameterValuesRef,
+        ) {
+            for (i, sample) in outputs[0].iter_mut().enumerate() {
+                *sample = context.time_at(i) as f32;
+            }
+        }
+    }
+
+    /// Delays its input by one block, with a delay line allocated in `prepare`.
+    struct BlockDelayProcessor {
+        delay: Vec<f32>,
+        sample_rate: f32,
+        deactivated: bool,
+    }
+
+    impl Processor for BlockDelayProcessor {
+        type Data = ();
+
+        fn new(_data: Self::Data) -> Self {
+            Self {
+                delay: Vec::new(),
+                sample_rate: 0.0,
+                deactivated: false,
+            }
+        }
+
+        fn prepare(&mut self, sample_rate: f32, max_block_size: usize) {
+            self.delay = vec![0.0; max_block_size];
+            self.sample_rate = sample_rate;
+        }
+
+        fn reset(&mut self) {
+            self.delay.fill(0.0);
+        }
+
+        fn on_deactivate(&mut self) {
+            self.deactivated = true;
+        }
+
+        fn tail_time(&self) -> f64 {
+            self.delay.len() as f64 / self.sample_rate as f64
+        }
+
+        fn process(
+            &mut self,
+            inputs: &[&[f32]],
+            outputs: &mut [&mut [f32]],
+            _context: &ProcessContext,
+            _params: &ParameterValuesRef,
+        ) {
+            for ((input, output), delayed) in inputs[0]
+                .iter()
+                .zip(outputs[0].iter_mut())
+                .zip(self.delay.iter_mut())
+            {
+                *output = std::mem::replace(delayed, *input);
+            }
+        }
+    }
+
+    #[test]
+    fn renders_with_default_parameters() {
+        let mut harness = RenderHarness::<GainProcessor>::new((), 48000.0, 1);
+        let input: Vec<f32> = (0..300).map(|i| i as f32).collect();
+
+        let outputs = harness.render(300, &[&input], &[]);
+
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0], input);
+    }
+
+    #[test]
+    fn zero_pads_short_inputs() {
+        let mut harness = RenderHarness::<GainProcessor>::new((), 48000.0, 1);
+
+        let outputs = harness.render(256, &[&[1.0; 10]], &[]);
+
+        assert_eq!(outputs[0][..10], [1.0; 10]);
+        assert!(outputs[0][10..].iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn a_rate_automation_is_applied_per_frame() {
+        let mut harness = RenderHarness::<GainProcessor>::new((), 48000.0, 1);
+        let gain: Vec<f32> = (0..256).map(|i| i as f32).collect();
+
+        let outputs = harness.render(256, &[&[1.0; 256]], &[("gain", &gain)]);
+
+        assert_eq!(outputs[0], gain);
+    }
+
+    #[test]
+    fn k_rate_automation_is_applied_per_block() {
+        let mut harness = RenderHarness::<GainProcessor>::new((), 48000.0, 1);
+        let offset: Vec<f32> = (0..256).map(|i| i as f32).collect();
+
+        let outputs = harness.render(256, &[], &[("offset", &offset)]);
+
+        assert!(outputs[0][..128].iter().all(|&sample| sample == 0.0));
+        assert!(outputs[0][128..].iter().all(|&sample| sample == 128.0));
+    }
+
+    #[test]
+    fn automation_holds_its_last_value() {
+        let mut harness = RenderHarness::<GainProcessor>::new((), 48000.0, 1);
+
+        let outputs = harness.render(256, &[&[1.0; 256]], &[("gain", &[2.0, 3.0])]);
+
+        assert_eq!(outputs[0][0], 2.0);
+        assert!(outputs[0][1..].iter().all(|&sample| sample == 3.0));
+    }
+
+    #[test]
+    fn inputs_and_outputs_are_grouped_by_port() {
+        let mut harness =
+            RenderHarness::<SidechainProcessor>::with_ports((), 48000.0, &[1, 1], &[1, 1]);
+
+        let outputs = harness.render(128, &[&[2.0; 128], &[0.5; 128]], &[]);
+
+        assert_eq!(outputs, vec![vec![1.0; 128], vec![0.5; 128]]);
+    }
+
+    #[test]
+    fn disconnected_inputs_do_not_shift_later_ports() {
+        let mut harness =
+            RenderHarness::<SidechainProcessor>::with_ports((), 48000.0, &[0, 1], &[1, 1]);
+
+        let outputs = harness.render(128, &[&[0.5; 128]], &[]);
+
+        assert_eq!(outputs, vec![vec![0.0; 128], vec![0.5; 128]]);
+    }
+
+    #[test]
+    fn messages_and_events_are_exchanged() {
+        let mut harness = RenderHarness::<LevelProcessor>::new(0.25, 48000.0, 1);
+
+        let before = harness.render(128, &[], &[]);
+        harness.send(0.5);
+        let after = harness.render(256, &[], &[]);
+
+        assert_eq!(before[0], vec![0.25; 128]);
+        assert_eq!(after[0], vec![0.5; 256]);
+        assert_eq!(harness.take_events(), vec![1, 2, 3]);
+        assert!(harness.take_events().is_empty());
+    }
+
+    #[test]
+    fn context_advances_with_each_block() {
+        let mut harness = RenderHarness::<ClockProcessor>::new((), 64.0, 1);
+        harness.schedule((), Timestamp::Frame(100));
+
+        let outputs = harness.render(384, &[], &[]);
+
+        assert_eq!(harness.current_frame(), 384);
+        assert_eq!(outputs[0][0], 0.0);
+        assert_eq!(outputs[0][100], 100.0 / 64.0);
+        assert_eq!(outputs[0][128], 2.0);
+        assert_eq!(outputs[0][383], 383.0 / 64.0);
+    }
+
+    #[test]
+    fn scheduled_messages_split_blocks() {
+        let mut harness = RenderHarness::<LevelProcessor>::new(0.0, 48000.0, 1);
+        harness.schedule(0.5, Timestamp::Time(300.0 / 48000.0));
+        harness.schedule(1.0, Timestamp::Frame(200));
+
+        let outputs = harness.render(384, &[], &[]);
+
+        assert!(outputs[0][..200].iter().all(|&sample| sample == 0.0));
+        assert!(outputs[0][200..300].iter().all(|&sample| sample == 1.0));
+        assert!(outputs[0][300..].iter().all(|&sample| sample == 0.5));
+    }
+
+    #[test]
+    fn scheduled_messages_wait_while_the_queue_is_full() {
+        let mut harness = RenderHarness::<LevelProcessor>::new(0.0, 48000.0, 1);
+        for _ in 0..QUEUE_CAPACITY {
+            harness.schedule(0.5, Timestamp::Frame(1000));
+        }
+        harness.render(128, &[], &[]);
+
+        // Held until the queue has room, then delivered at the start of the next block
+        harness.schedule(1.0, Timestamp::Frame(200));
+        let outputs = harness.render(1024, &[], &[]);
+
+        assert!(outputs[0][..872].iter().all(|&sample| sample == 0.0));
+        assert!(outputs[0][872..896].iter().all(|&sample| sample == 0.5));
+        assert!(outputs[0][896..].iter().all(|&sample| sample == 1.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "no parameter named `missing`")]
+    fn unknown_parameters_panic() {
+        let mut harness = RenderHarness::<GainProcessor>::new((), 48000.0, 1);
+        harness.set_parameter("missing", &[1.0]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "implements neither")]
+    fn processors_without_process_panic_in_debug_builds() {
+        let mut harness = RenderHarness::<SilentProcessor>::new((), 48000.0, 1);
+        harness.render(128, &[], &[]);
+    }
+
+    #[test]
+    fn unexpected_parameter_lengths_do_not_panic() {
+        let mut harness = RenderHarness::<GainProcessor>::new((), 48000.0, 1);
+        let input = [1.0; RENDER_QUANTUM_SIZE];
+        let mut output = [0.0; RENDER_QUANTUM_SIZE];
+
+        // Short arrays hold their last value
+        harness.set_parameter("gain", &[2.0, 3.0]);
+        harness.process_block(&[&input], &mut [&mut output]);
+        assert_eq!(output[0], 2.0);
+        assert!(output[1..].iter().all(|&sample| sample == 3.0));
+
+        // Empty arrays keep the previous values
+        harness.set_parameter("gain", &[]);
+        harness.process_block(&[&input], &mut [&mut output]);
+        assert!(output[1..].iter().all(|&sample| sample == 3.0));
+
+        // Long arrays are truncated to the block
+        harness.set_parameter("gain", &[4.0; 2 * RENDER_QUANTUM_SIZE]);
+        harness.process_block(&[&input], &mut [&mut output]);
+        assert!(output.iter().all(|&sample| sample == 4.0));
+    }
+
+    #[test]
+    fn mismatched_channel_lengths_do_not_panic() {
+        let mut harness = RenderHarness::<GainProcessor>::new((), 48000.0, 2);
+        let long = [1.0; 2 * RENDER_QUANTUM_SIZE];
+        let short = [2.0; 10];
+        let mut long_output = [-1.0; 2 * RENDER_QUANTUM_SIZE];
+        let mut short_output = [-1.0; 10];
+
+        harness.process_block(&[&long, &short], &mut [&mut long_output, &mut short_output]);
+
+        // Long inputs are truncated to the block, short ones zero-padded
+        assert!(long_output[..RENDER_QUANTUM_SIZE]
+            .iter()
+            .all(|&sample| sample == 1.0));
+        // Long outputs receive the block, short ones its first frames
+        assert!(long_output[RENDER_QUANTUM_SIZE..]
+            .iter()
+            .all(|&sample| sample == -1.0));
+        assert_eq!(short_output, [2.0; 10]);
+
+        let mut output = [0.0; RENDER_QUANTUM_SIZE];
+        let mut zeroed = [-1.0; RENDER_QUANTUM_SIZE];
+        harness.process_block(&[&long, &short], &mut [&mut output, &mut zeroed]);
+        assert_eq!(zeroed[..10], [2.0; 10]);
+        assert!(zeroed[10..].iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn lifecycle_hooks_are_called() {
+        let mut harness = RenderHarness::<BlockDelayProcessor>::new((), 48000.0, 1);
+        assert_eq!(harness.processor().delay.len(), RENDER_QUANTUM_SIZE);
+
+        let input = vec![1.0; RENDER_QUANTUM_SIZE];
+        let outputs = harness.render(RENDER_QUANTUM_SIZE, &[&input], &[]);
+        assert!(outputs[0].iter().all(|&sample| sample == 0.0));
+
+        harness.reset();
+        let outputs = harness.render(RENDER_QUANTUM_SIZE, &[&input], &[]);
+        assert!(outputs[0].iter().all(|&sample| sample == 0.0));
+        let outputs = harness.render(RENDER_QUANTUM_SIZE, &[&input], &[]);
+        assert!(outputs[0].iter().all(|&sample| sample == 1.0));
+
+        // The tail is rendered after deactivation
+        harness.deactivate();
+        let silence = vec![0.0; RENDER_QUANTUM_SIZE];
+        let outputs = harness.render(RENDER_QUANTUM_SIZE, &[&silence], &[]);
+        assert!(outputs[0].iter().all(|&sample| sample == 1.0));
+        assert!(harness.is_stopped());
+        assert!(harness.processor().deactivated);
+    }
+
+    #[test]
+    fn block_size_can_be_changed() {
+        let mut harness = RenderHarness::<GainProcessor>::new((), 48000.0, 1);
+        harness.set_block_size(256);
+        assert_eq!(harness.block_size(), 256);
+
+        let input = vec![1.0; 512];
+        let automation: Vec<f32> = (0..512).map(|i| i as f32).collect();
+        let outputs = harness.render(512, &[&input], &[("gain", &automation)]);
+        assert_eq!(outputs[0], automation);
+
+        let mut harness = RenderHarness::<BlockDelayProcessor>::new((), 48000.0, 1);
+        harness.set_block_size(256);
+        assert_eq!(harness.processor().delay.len(), 256);
+        let outputs = harness.render(256, &[&input[..256]], &[]);
+        assert_eq!(outputs[0].len(), 256);
+        assert!(outputs[0].iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn deactivated_processors_fade_out() {
+        let mut harness = RenderHarness::<LevelProcessor>::new(1.0, 48000.0, 1);
+        harness.deactivate();
+
+        let outputs = harness.render(3 * RENDER_QUANTUM_SIZE, &[], &[]);
+
+        let (block, fade) = outputs[0].split_at(RENDER_QUANTUM_SIZE);
+        assert!(block.iter().all(|&sample| sample == 1.0));
+        assert_eq!(fade[0], 1.0);
+        assert_eq!(fade[32], 0.5);
+        assert!(fade[64..].iter().all(|&sample| sample == 0.0));
+        assert!(harness.is_stopped());
+        assert_eq!(harness.processor().blocks, 2);
+    }
+
+    #[test]
+    fn bypass_crossfades_to_the_dry_input() {
+        let mut harness = RenderHarness::<LevelProcessor>::new(0.5, 48000.0, 1);
+        harness.set_fade_length(64);
+        harness.set_bypass(true);
+        let input = vec![1.0; 2 * RENDER_QUANTUM_SIZE];
+
+        let outputs = harness.render(RENDER_QUANTUM_SIZE, &[&input], &[]);
+
+        assert_eq!(outputs[0][0], 0.5);
+        assert_eq!(outputs[0][32], 0.75);
+        assert!(outputs[0][64..].iter().all(|&sample| sample == 1.0));
+
+        harness.set_bypass(false);
+        let outputs = harness.render(RENDER_QUANTUM_SIZE, &[&input], &[]);
+
+        assert_eq!(outputs[0][0], 1.0);
+        assert!(outputs[0][64..].iter().all(|&sample| sample == 0.5));
+    }
+}
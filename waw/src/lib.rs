@@ -3,32 +3,90 @@
 #![doc = include_str!("../../README.md")]
 #![warn(missing_docs)]
 
+// Allows the derive macros to refer to `::waw` from within this crate.
+extern crate self as waw;
+
 /// Audio buffer utilities for input/output and parameter conversion.
 pub mod buffer;
 
+/// Delay compensation for aligning parallel paths.
+mod compensation;
+
+/// Port and channel configuration of processor nodes.
+pub mod config;
+
+/// Errors reported by failed processors.
+pub mod error;
+
+/// Offline render harness for testing processors without a browser.
+pub mod harness;
+
+/// Declicking fades and bypass applied around processors.
+mod fade;
+
 /// Macros for processor registration and code generation.
 pub mod macros;
 
+/// Typed message channels between nodes and processors.
+mod message;
+
 /// Node wrapper for proper cleanup and lifecycle management.
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
 pub mod node;
 
 /// Parameter types and JS conversion utilities for audio processing.
 pub mod parameter;
 
+/// Conversions between parameter values and a normalized `0..1` range.
+pub mod mapping;
+
+/// Views over audio channels grouped by node input and output.
+pub mod port;
+
 /// Core audio processor trait and parameter types.
 pub mod processor;
 
+/// Lock-free ring buffer for streaming data between threads.
+pub mod ring;
+
+/// Smoothing of parameter values.
+pub mod smoothing;
+
+/// Sample-accurate scheduling of messages to processors.
+pub mod schedule;
+
+/// Tail-aware shutdown of deactivated processors.
+mod tail;
+
+/// TypeScript declarations for registered processors.
+pub mod typescript;
+
 /// Processor registration and node creation utilities.
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
 pub mod registry;
 
 /// Wrapper for integrating processors with the Web Audio API.
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
 pub mod wrapper;
 
-pub use buffer::ParameterValuesRef;
+pub use buffer::{ParameterValue, ParameterValuesRef, RENDER_QUANTUM_SIZE};
+pub use compensation::{DelayCompensation, DelayCompensationData};
+pub use config::{ChannelCountMode, ChannelInterpretation, NodeConfig};
+pub use error::ProcessorError;
+pub use harness::RenderHarness;
+pub use mapping::ParameterMapping;
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
 pub use node::AudioWorkletNodeWrapper;
 pub use parameter::*;
+pub use port::Ports;
 pub use processor::*;
-pub use registry::{create_node, register_all};
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+pub use registry::{create_node, register, register_all, Registered, RegistrationReport};
+pub use ring::{Consumer, Producer, RingBuffer};
+pub use schedule::Timestamp;
+pub use smoothing::{Smoother, Smoothing};
+pub use waw_macros::{Node, Parameters};
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
 pub use wrapper::{ProcessorWrapper, ProcessorWrapperData};
 
 // Re-export wasm-bindgen for macros
@@ -36,4 +94,5 @@ pub use inventory;
 pub use js_sys;
 pub use wasm_bindgen;
 pub use web_sys;
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
 pub use web_thread;
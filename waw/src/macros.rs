@@ -2,7 +2,10 @@
 ///
 /// This macro generates the necessary boilerplate code to register a processor type
 /// with the AudioWorkletGlobalScope and provides a convenient `create_node` method
-/// for instantiating the processor in an AudioContext.
+/// for instantiating the processor in an AudioContext. It also implements
+/// `waw::registry::Registered`, so the processor can be registered by type, and records the
+/// processor for [`typescript::declarations`](crate::typescript::declarations). On targets other
+/// than `wasm32-unknown-unknown`, only the declaration is generated.
 ///
 /// # Arguments
 ///
@@ -25,6 +28,7 @@
 macro_rules! register {
     ($processor:ty, $name:literal) => {
         // Create the registration function
+        #[cfg(all(target_family = "wasm", target_os = "unknown"))]
         fn register_processor() -> Result<(), $crate::wasm_bindgen::JsValue> {
             use $crate::wasm_bindgen::JsCast;
             use $crate::web_thread::web::audio_worklet::AudioWorkletGlobalScopeExt;
@@ -36,19 +40,75 @@ macro_rules! register {
                 .map_err(|e| $crate::wasm_bindgen::JsValue::from_str(&format!("{:?}", e)))
         }
 
+        #[cfg(all(target_family = "wasm", target_os = "unknown"))]
         $crate::inventory::submit! {
-            $crate::registry::ProcessorRegistration::new($name, register_processor)
+            $crate::registry::ProcessorRegistration::new(
+                $name,
+                register_processor,
+                <$processor as $crate::Processor>::parameter_descriptors,
+            )
         }
 
+        // Collected on every target so declarations can be generated natively
+        $crate::inventory::submit! {
+            $crate::typescript::ProcessorDeclaration::new(
+                $name,
+                <$processor as $crate::Processor>::parameter_descriptors,
+                <<$processor as $crate::Processor>::Message as $crate::typescript::TypeScript>::typescript_type,
+                <<$processor as $crate::Processor>::Event as $crate::typescript::TypeScript>::typescript_type,
+            )
+        }
+
+        #[cfg(all(target_family = "wasm", target_os = "unknown"))]
+        impl $crate::registry::Registered for $processor {
+            const NAME: &'static str = $name;
+        }
+
+        #[cfg(all(target_family = "wasm", target_os = "unknown"))]
         impl $processor {
             /// Create a new audio worklet node for this processor
             pub fn create_node(
-                ctx: &$crate::web_sys::AudioContext,
+                ctx: &$crate::web_sys::BaseAudioContext,
                 data: <$processor as $crate::Processor>::Data,
                 options: Option<&$crate::web_sys::AudioWorkletNodeOptions>,
-            ) -> Result<$crate::AudioWorkletNodeWrapper, $crate::wasm_bindgen::JsValue> {
+            ) -> Result<$crate::AudioWorkletNodeWrapper<$processor>, $crate::wasm_bindgen::JsValue>
+            {
                 $crate::create_node::<$processor>(ctx, $name, data, options)
             }
         }
     };
 }
+
+/// Declares a type exported by `wasm-bindgen` for [`typescript::declarations`](crate::typescript::declarations).
+///
+/// Implements [`TypeScript`](crate::typescript::TypeScript) for the type, so it can be used in
+/// message and event types. The declarations refer to it by name and import it from the
+/// bindings; give the name explicitly if it differs, e.g. when set with `js_name`.
+///
+/// # Example
+///
+/// ```ignore
+/// #[wasm_bindgen]
+/// pub enum Waveform {
+///     Sine,
+///     Square,
+/// }
+///
+/// waw::typescript_binding!(Waveform);
+/// ```
+#[macro_export]
+macro_rules! typescript_binding {
+    ($ty:ident) => {
+        $crate::typescript_binding!($ty, ::core::stringify!($ty));
+    };
+    ($ty:ty, $name:expr) => {
+        impl $crate::typescript::TypeScript for $ty {
+            fn typescript_type(
+                imports: &mut ::std::collections::BTreeSet<::std::string::String>,
+            ) -> ::std::string::String {
+                imports.insert(::std::string::ToString::to_string($name));
+                ::std::string::ToString::to_string($name)
+            }
+        }
+    };
+}
@@ -0,0 +1,225 @@
+/// How a parameter's range maps to a normalized `0..1` range, e.g. for MIDI CCs and generic
+/// knobs.
+///
+/// Set in [`ParameterDescriptor::mapping`](crate::ParameterDescriptor::mapping) and used through
+/// [`ParameterDescriptor::to_normalized`](crate::ParameterDescriptor::to_normalized) and
+/// [`ParameterDescriptor::from_normalized`](crate::ParameterDescriptor::from_normalized). The
+/// conversions do not allocate, so processors can use them while rendering.
+///
+/// # Example
+/// ```
+/// use waw::ParameterMapping;
+///
+/// let cutoff = ParameterMapping::Logarithmic;
+/// assert_eq!(cutoff.from_normalized(0.5, 10.0, 1000.0), 100.0);
+/// assert!((cutoff.to_normalized(100.0, 10.0, 1000.0) - 0.5).abs() < 1e-6);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ParameterMapping {
+    /// Values are spread evenly.
+    #[default]
+    Linear,
+    /// Each octave or decade takes the same part of the range, e.g. for frequencies.
+    ///
+    /// Falls back to [`ParameterMapping::Linear`] unless both bounds are positive.
+    Logarithmic,
+    /// Normalized values are raised to `1 / skew`: a skew below 1 gives more of the range to
+    /// small values, above 1 to large values.
+    Skewed {
+        /// The skew factor, positive.
+        skew: f32,
+    },
+    /// Values are amplitude gains, spread evenly in decibels from `min_db` to the maximum.
+    /// A normalized value of 0 maps to the minimum, e.g. silence.
+    ///
+    /// Falls back to [`ParameterMapping::Linear`] unless the maximum is positive.
+    Decibels {
+        /// Level at the bottom of the range, in decibels.
+        min_db: f32,
+    },
+    /// Values are spread evenly and rounded to multiples of `step` from the minimum, e.g. for
+    /// discrete parameters.
+    Stepped {
+        /// Distance between two values, positive.
+        step: f32,
+    },
+}
+
+impl ParameterMapping {
+    /// Converts `value`, clamped to `min..=max`, to a normalized value in `0..=1`.
+    pub fn to_normalized(&self, value: f32, min: f32, max: f32) -> f32 {
+        if max <= min {
+            return 0.0;
+        }
+        // Computed in `f64`, so the width of ranges such as `f32::MIN..f32::MAX` stays finite
+        let value = f64::from(value.clamp(min, max));
+        let (min, max) = (f64::from(min), f64::from(max));
+
+        let normalized = match *self {
+            ParameterMapping::Logarithmic if min > 0.0 => (value / min).ln() / (max / min).ln(),
+            ParameterMapping::Skewed { skew } if skew > 0.0 => {
+                ((value - min) / (max - min)).powf(f64::from(skew))
+            }
+            ParameterMapping::Decibels { min_db } if max > 0.0 => {
+                let (min_db, max_db) = (f64::from(min_db), gain_to_db(max));
+                if value <= min || value <= 0.0 || max_db <= min_db {
+                    0.0
+                } else {
+                    (gain_to_db(value) - min_db) / (max_db - min_db)
+                }
+            }
+            ParameterMapping::Stepped { step } if step > 0.0 => {
+                (quantize(value, min, max, f64::from(step)) - min) / (max - min)
+            }
+            _ => (value - min) / (max - min),
+        };
+        (normalized as f32).clamp(0.0, 1.0)
+    }
+
+    /// Converts `normalized`, clamped to `0..=1`, to a value in `min..=max`.
+    pub fn from_normalized(&self, normalized: f32, min: f32, max: f32) -> f32 {
+        if max <= min {
+            return min;
+        }
+        let normalized = f64::from(normalized.clamp(0.0, 1.0));
+        let (low, high) = (f64::from(min), f64::from(max));
+
+        let value = match *self {
+            ParameterMapping::Logarithmic if low > 0.0 => low * (high / low).powf(normalized),
+            ParameterMapping::Skewed { skew } if skew > 0.0 => {
+                low + (high - low) * normalized.powf(1.0 / f64::from(skew))
+            }
+            ParameterMapping::Decibels { min_db } if high > 0.0 => {
+                let (min_db, max_db) = (f64::from(min_db), gain_to_db(high));
+                if normalized == 0.0 || max_db <= min_db {
+                    low
+                } else {
+                    db_to_gain(min_db + normalized * (max_db - min_db))
+                }
+            }
+            ParameterMapping::Stepped { step } if step > 0.0 => {
+                quantize(low + normalized * (high - low), low, high, f64::from(step))
+            }
+            _ => low + normalized * (high - low),
+        };
+        (value as f32).clamp(min, max)
+    }
+}
+
+/// Rounds `value` to the nearest multiple of `step` from `min` within `min..=max`.
+fn quantize(value: f64, min: f64, max: f64, step: f64) -> f64 {
+    let steps = ((max - min) / step).floor();
+    min + ((value - min) / step).round().min(steps) * step
+}
+
+fn gain_to_db(gain: f64) -> f64 {
+    20.0 * gain.log10()
+}
+
+fn db_to_gain(db: f64) -> f64 {
+    10.0f64.powf(db / 20.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn mappings_round_trip() {
+        let mappings = [
+            (ParameterMapping::Linear, -1.0),
+            (ParameterMapping::Logarithmic, 0.5),
+            (ParameterMapping::Skewed { skew: 0.3 }, 0.5),
+            (ParameterMapping::Decibels { min_db: -60.0 }, 0.0),
+        ];
+        for (mapping, min) in mappings {
+            for normalized in [0.0, 0.25, 0.5, 1.0] {
+                let value = mapping.from_normalized(normalized, min, 2.0);
+                assert_close(mapping.to_normalized(value, min, 2.0), normalized);
+            }
+        }
+    }
+
+    #[test]
+    fn mappings_shape_the_range() {
+        assert_close(
+            ParameterMapping::Linear.from_normalized(0.25, -1.0, 1.0),
+            -0.5,
+        );
+        assert_close(
+            ParameterMapping::Skewed { skew: 0.5 }.from_normalized(0.5, 0.0, 100.0),
+            25.0,
+        );
+        // -60 dB to about +6 dB, so 0.5 is about -27 dB
+        let decibels = ParameterMapping::Decibels { min_db: -60.0 };
+        assert_close(
+            decibels.from_normalized(0.5, 0.0, 2.0),
+            db_to_gain((-60.0 + gain_to_db(2.0)) / 2.0) as f32,
+        );
+        assert_eq!(decibels.from_normalized(0.0, 0.0, 2.0), 0.0);
+        assert_eq!(decibels.to_normalized(0.0, 0.0, 2.0), 0.0);
+    }
+
+    #[test]
+    fn stepped_values_are_quantized() {
+        let stepped = ParameterMapping::Stepped { step: 1.0 };
+        assert_eq!(stepped.from_normalized(0.0, 0.0, 3.0), 0.0);
+        assert_eq!(stepped.from_normalized(0.4, 0.0, 3.0), 1.0);
+        assert_eq!(stepped.from_normalized(1.0, 0.0, 3.0), 3.0);
+        assert_eq!(stepped.to_normalized(2.2, 0.0, 3.0), 2.0 / 3.0);
+    }
+
+    #[test]
+    fn values_are_clamped() {
+        let mapping = ParameterMapping::Linear;
+        assert_eq!(mapping.from_normalized(1.5, 0.0, 10.0), 10.0);
+        assert_eq!(mapping.to_normalized(-5.0, 0.0, 10.0), 0.0);
+        // Logarithmic falls back to linear for ranges that include 0
+        assert_eq!(
+            ParameterMapping::Logarithmic.from_normalized(0.5, 0.0, 10.0),
+            5.0
+        );
+    }
+
+    #[test]
+    fn full_float_range_stays_finite() {
+        let mappings = [
+            ParameterMapping::Linear,
+            ParameterMapping::Skewed { skew: 0.5 },
+            ParameterMapping::Stepped { step: 1.0 },
+        ];
+        for mapping in mappings {
+            assert_eq!(mapping.to_normalized(f32::MIN, f32::MIN, f32::MAX), 0.0);
+            assert_eq!(mapping.to_normalized(f32::MAX, f32::MIN, f32::MAX), 1.0);
+            assert_eq!(mapping.from_normalized(1.0, f32::MIN, f32::MAX), f32::MAX);
+            let middle = mapping.to_normalized(0.0, f32::MIN, f32::MAX);
+            assert!(middle.is_finite(), "{mapping:?} gave {middle}");
+        }
+        assert_eq!(
+            ParameterMapping::Linear.from_normalized(0.5, f32::MIN, f32::MAX),
+            0.0
+        );
+    }
+
+    #[test]
+    fn decibels_without_positive_maximum_are_linear() {
+        let decibels = ParameterMapping::Decibels { min_db: -60.0 };
+        for (min, max) in [(-2.0, 0.0), (-2.0, -1.0)] {
+            for normalized in [0.0, 0.5, 1.0] {
+                let value = decibels.from_normalized(normalized, min, max);
+                assert_eq!(
+                    value,
+                    ParameterMapping::Linear.from_normalized(normalized, min, max)
+                );
+                assert_close(decibels.to_normalized(value, min, max), normalized);
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{
+    fade::Declick,
+    processor::Processor,
+    ring::{Consumer, Producer, RingBuffer},
+    schedule::{EventQueue, Timestamp},
+};
+use std::cell::RefCell;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Number of commands or events that can wait in a channel.
+///
+/// Channels are allocated up front so the audio thread never allocates to pass them on. Once
+/// a channel is full, further commands are rejected and further events dropped until the
+/// other side catches up.
+pub(crate) const CHANNEL_CAPACITY: usize = 1024;
+
+/// A command sent from the main thread to the processor wrapper.
+pub(crate) enum Command<M> {
+    /// A message, delivered either before the next block or at a given time.
+    Message { message: M, at: Option<Timestamp> },
+    /// Clears the processor's state and any messages still waiting for their time.
+    Reset,
+    /// Crossfades to the dry input, or back to the processor's output.
+    Bypass(bool),
+    /// Sets the length of fades and crossfades, in frames.
+    FadeLength(usize),
+}
+
+/// Creates a channel for commands sent from the main thread to the processor wrapper.
+pub(crate) fn command_channel<M>() -> (Producer<Command<M>>, Consumer<Command<M>>) {
+    RingBuffer::new(CHANNEL_CAPACITY).split()
+}
+
+/// Delivers the commands waiting in `commands` before a block is rendered, queueing
+/// scheduled messages until their frame.
+///
+/// While the scheduled queue is full, commands stay in the channel from the next scheduled
+/// message on, and are delivered in order once earlier messages have made room.
+pub(crate) fn deliver_commands<P: Processor>(
+    commands: &mut Consumer<Command<P::Message>>,
+    processor: &mut P,
+    queue: &mut EventQueue<P::Message>,
+    declick: &mut Declick,
+    sample_rate: f32,
+) {
+    loop {
+        let scheduled = matches!(commands.peek(), Some(Command::Message { at: Some(_), .. }));
+        if scheduled && queue.is_full() {
+            break;
+        }
+        let Some(command) = commands.pop() else {
+            break;
+        };
+
+        match command {
+            Command::Message { message, at: None } => processor.on_message(message),
+            Command::Message {
+                message,
+                at: Some(at),
+            } => {
+                // The queue has room, checked above
+                let _ = queue.push(at.to_frame(sample_rate), message);
+            }
+            Command::Reset => {
+                queue.clear();
+                processor.reset();
+            }
+            Command::Bypass(bypassed) => declick.set_bypass(bypassed),
+            Command::FadeLength(frames) => declick.set_fade_frames(frames),
+        }
+    }
+}
+
+/// Creates a channel for events posted from a processor to the main thread.
+///
+/// Events are passed through shared memory; the `pending` flag tracks whether the main thread
+/// has already been notified, so the processor only rings it once per batch of events.
+pub(crate) fn event_channel<E>() -> (EventSender<E>, EventReceiver<E>) {
+    let (producer, consumer) = RingBuffer::new(CHANNEL_CAPACITY).split();
+    let pending = Arc::new(AtomicBool::new(false));
+    (
+        EventSender {
+            producer,
+            pending: pending.clone(),
+        },
+        EventReceiver {
+            consumer: RefCell::new(consumer),
+            pending,
+        },
+    )
+}
+
+/// Sending half of an event channel, owned by the processor wrapper.
+pub(crate) struct EventSender<E> {
+    producer: Producer<E>,
+    pending: Arc<AtomicBool>,
+}
+
+impl<E> EventSender<E> {
+    /// Sends an event, returning `true` if the main thread needs to be notified.
+    ///
+    /// The event is dropped if [`CHANNEL_CAPACITY`] events are already waiting.
+    pub(crate) fn send(&mut self, event: E) -> bool {
+        if self.producer.push(event).is_err() {
+            // The main thread is not keeping up, or nobody is listening.
+            return false;
+        }
+        !self.pending.swap(true, Ordering::AcqRel)
+    }
+}
+
+/// Receiving half of an event channel, owned by the node wrapper.
+pub(crate) struct EventReceiver<E> {
+    consumer: RefCell<Consumer<E>>,
+    pending: Arc<AtomicBool>,
+}
+
+impl<E> EventReceiver<E> {
+    /// Marks the current notification as handled, so the next event notifies again.
+    /// Call before draining events with [`EventReceiver::try_recv`].
+    pub(crate) fn acknowledge(&self) {
+        self.pending.store(false, Ordering::Release);
+    }
+
+    /// Receives the next event, if any.
+    pub(crate) fn try_recv(&self) -> Option<E> {
+        self.consumer.borrow_mut().pop()
+    }
+}
@@ -1,24 +1,199 @@
+use crate::error::{ErrorSlot, ProcessorError};
+use crate::message::{Command, EventReceiver};
+use crate::parameter::ParameterDescriptor;
+use crate::processor::Processor;
+use crate::ring::Producer;
+use crate::schedule::Timestamp;
+use std::cell::{Cell, RefCell};
 use std::mem::ManuallyDrop;
 use std::ops::Deref;
+use std::rc::{Rc, Weak};
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
+    atomic::{AtomicBool, AtomicUsize, Ordering},
     Arc,
 };
-use web_sys::AudioWorkletNode;
+use wasm_bindgen::{closure::Closure, prelude::wasm_bindgen, JsCast, JsValue};
+use web_sys::{AudioParam, AudioWorkletNode, MessagePort};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setInterval)]
+    fn set_interval(handler: &js_sys::Function, timeout: i32) -> i32;
+
+    #[wasm_bindgen(js_name = clearInterval)]
+    fn clear_interval(handle: i32);
+}
+
+/// Interval at which the latency is polled while a latency callback is set, in milliseconds.
+const LATENCY_POLL_INTERVAL: i32 = 50;
 
 /// A wrapper around `AudioWorkletNode` that signals the processor to stop when dropped.
 ///
 /// This ensures that when the node is dropped on the main thread, the processor running
-/// in the AudioWorklet thread will stop processing on the next process call.
-pub struct AudioWorkletNodeWrapper {
+/// in the AudioWorklet thread stops processing once its inputs are silent and its tail has
+/// finished (see [`Processor::tail_time`]).
+///
+/// The wrapper is also the main thread end of the processor's message channels:
+/// use [`AudioWorkletNodeWrapper::send`] to send [`Processor::Message`]s and
+/// [`AudioWorkletNodeWrapper::set_on_event`] to receive [`Processor::Event`]s.
+/// If the processor fails, the error is available from [`AudioWorkletNodeWrapper::error`]
+/// and [`AudioWorkletNodeWrapper::set_on_error`].
+pub struct AudioWorkletNodeWrapper<P: Processor> {
     node: AudioWorkletNode,
     is_active: Arc<AtomicBool>,
+    /// Commands waiting for the processor, shared by clones of the wrapper.
+    messages: Rc<RefCell<Producer<Command<P::Message>>>>,
+    inbox: Rc<Inbox<P::Event>>,
+}
+
+/// JS callback invoked when the processor notifies the node.
+type Notification = Closure<dyn FnMut()>;
+
+/// Callback set by the application, taken out of its cell while it runs.
+type Callback<T> = RefCell<Option<Box<dyn FnMut(T)>>>;
+
+/// Main thread state for receiving events and errors from the processor.
+struct Inbox<E> {
+    node: AudioWorkletNode,
+    port: MessagePort,
+    events: EventReceiver<E>,
+    error: Arc<ErrorSlot>,
+    /// Set once the error has been passed to the error callback.
+    error_reported: Cell<bool>,
+    /// Latency reported by the processor, in frames.
+    latency: Arc<AtomicUsize>,
+    /// Latency last passed to the latency callback.
+    reported_latency: Cell<usize>,
+    /// Interval polling the latency, started when a latency callback is first set.
+    latency_poll: Cell<Option<i32>>,
+    on_event: Callback<E>,
+    on_error: Callback<ProcessorError>,
+    on_latency_change: Callback<usize>,
+    /// Closures attached to the port and the node, kept alive until the inbox is dropped.
+    notifications: RefCell<Vec<Notification>>,
+}
+
+impl<E> Inbox<E> {
+    /// Passes waiting events to the event callback, if one is set.
+    fn dispatch_events(&self) {
+        let Some(mut callback) = self.on_event.take() else {
+            return;
+        };
+
+        self.events.acknowledge();
+        while let Some(event) = self.events.try_recv() {
+            callback(event);
+        }
+
+        // Keep a callback set from within the callback
+        self.on_event.borrow_mut().get_or_insert(callback);
+    }
+
+    /// Passes the processor error to the error callback, once.
+    fn dispatch_error(&self) {
+        if self.error_reported.get() {
+            return;
+        }
+        let Some(error) = self.error.get() else {
+            return;
+        };
+        let Some(mut callback) = self.on_error.take() else {
+            return;
+        };
+
+        self.error_reported.set(true);
+        callback(error);
+
+        self.on_error.borrow_mut().get_or_insert(callback);
+    }
+
+    /// Passes the processor's latency to the latency callback if it has changed.
+    fn dispatch_latency(&self) {
+        let latency = self.latency.load(Ordering::Acquire);
+        if latency == self.reported_latency.get() {
+            return;
+        }
+        let Some(mut callback) = self.on_latency_change.take() else {
+            return;
+        };
+
+        self.reported_latency.set(latency);
+        callback(latency);
+
+        self.on_latency_change.borrow_mut().get_or_insert(callback);
+    }
+}
+
+impl<E> Drop for Inbox<E> {
+    fn drop(&mut self) {
+        // Detach the callbacks before their closures are freed.
+        self.port.set_onmessage(None);
+        self.node.set_onprocessorerror(None);
+        if let Some(handle) = self.latency_poll.take() {
+            clear_interval(handle);
+        }
+    }
 }
 
-impl AudioWorkletNodeWrapper {
+impl<P: Processor> AudioWorkletNodeWrapper<P> {
     /// Creates a new wrapper around an AudioWorkletNode with a shared active state.
-    pub(crate) fn new(node: AudioWorkletNode, is_active: Arc<AtomicBool>) -> Self {
-        Self { node, is_active }
+    pub(crate) fn new(
+        node: AudioWorkletNode,
+        is_active: Arc<AtomicBool>,
+        messages: Producer<Command<P::Message>>,
+        events: EventReceiver<P::Event>,
+        error: Arc<ErrorSlot>,
+        latency: Arc<AtomicUsize>,
+    ) -> Result<Self, JsValue> {
+        let inbox = Rc::new(Inbox {
+            node: node.clone(),
+            port: node.port()?,
+            events,
+            error,
+            error_reported: Cell::new(false),
+            latency,
+            reported_latency: Cell::new(0),
+            latency_poll: Cell::new(None),
+            on_event: RefCell::new(None),
+            on_error: RefCell::new(None),
+            on_latency_change: RefCell::new(None),
+            notifications: RefCell::new(Vec::new()),
+        });
+
+        // The processor rings the port when events are waiting or it caught a panic
+        let weak = Rc::downgrade(&inbox);
+        let on_message = Notification::new(move || {
+            if let Some(inbox) = Weak::upgrade(&weak) {
+                inbox.dispatch_events();
+                inbox.dispatch_error();
+            }
+        });
+
+        // The browser reports processors that trapped, e.g. after a panic that could not be
+        // caught, whose message the panic hook recorded beforehand
+        let weak = Rc::downgrade(&inbox);
+        let on_processor_error = Notification::new(move || {
+            if let Some(inbox) = Weak::upgrade(&weak) {
+                inbox.error.set(ProcessorError::Aborted);
+                inbox.dispatch_error();
+            }
+        });
+
+        inbox
+            .port
+            .set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        node.set_onprocessorerror(Some(on_processor_error.as_ref().unchecked_ref()));
+        inbox
+            .notifications
+            .borrow_mut()
+            .extend([on_message, on_processor_error]);
+
+        Ok(Self {
+            node,
+            is_active,
+            messages: Rc::new(RefCell::new(messages)),
+            inbox,
+        })
     }
 
     /// Returns a reference to the underlying AudioWorkletNode.
@@ -26,6 +201,155 @@ impl AudioWorkletNodeWrapper {
         &self.node
     }
 
+    /// Returns the AudioParam with the given name.
+    pub fn parameter(&self, name: &str) -> Result<AudioParam, JsValue> {
+        self.node
+            .parameters()?
+            .get(name)
+            .ok_or_else(|| JsValue::from_str(&format!("Node has no parameter named `{name}`")))
+    }
+
+    /// Returns the descriptor of the processor's parameter with the given name.
+    pub fn parameter_descriptor(name: &str) -> Result<ParameterDescriptor, JsValue> {
+        P::parameter_descriptors()
+            .into_iter()
+            .find(|descriptor| descriptor.name == name)
+            .ok_or_else(|| JsValue::from_str(&format!("Processor has no parameter named `{name}`")))
+    }
+
+    /// Sets the value of the AudioParam with the given name from a normalized value in `0..=1`,
+    /// following the parameter's [`mapping`](ParameterDescriptor::mapping).
+    pub fn set_normalized(&self, name: &str, normalized: f32) -> Result<(), JsValue> {
+        let descriptor = Self::parameter_descriptor(name)?;
+        self.parameter(name)?
+            .set_value(descriptor.from_normalized(normalized));
+        Ok(())
+    }
+
+    /// Returns the value of the AudioParam with the given name as a normalized value in `0..=1`.
+    pub fn normalized(&self, name: &str) -> Result<f32, JsValue> {
+        let descriptor = Self::parameter_descriptor(name)?;
+        Ok(descriptor.to_normalized(self.parameter(name)?.value()))
+    }
+
+    /// Sends a message to the processor.
+    ///
+    /// The message is delivered to [`Processor::on_message`] before the next block is rendered.
+    /// Messages wait in a queue allocated with the node, so the processor never allocates to
+    /// receive them; this fails if 1024 messages are already waiting.
+    pub fn send(&self, message: P::Message) -> Result<(), JsValue> {
+        self.post(Command::Message { message, at: None })
+    }
+
+    /// Schedules a message to be delivered to the processor at an exact frame or context time.
+    ///
+    /// The message is delivered to [`Processor::handle_event`] before the frame at `at` is
+    /// rendered. Messages scheduled in the past are delivered before the next block.
+    ///
+    /// Up to 1024 scheduled messages can wait for their time. Further messages stay in the
+    /// message queue, after which [`AudioWorkletNodeWrapper::send`] and this fail, and are
+    /// delivered late if their time passes before earlier messages have made room.
+    pub fn schedule(&self, message: P::Message, at: Timestamp) -> Result<(), JsValue> {
+        self.post(Command::Message {
+            message,
+            at: Some(at),
+        })
+    }
+
+    /// Clears the processor's state without recreating the node.
+    ///
+    /// [`Processor::reset`] is called before the next block, after any messages sent before it.
+    /// Scheduled messages that have not been delivered yet are dropped.
+    pub fn reset(&self) -> Result<(), JsValue> {
+        self.post(Command::Reset)
+    }
+
+    /// Bypasses the processor, crossfading its output to the dry input.
+    ///
+    /// Output `n` is bypassed to input `n`, channel by channel; output channels without a
+    /// matching input channel are silent. The processor keeps rendering while bypassed, so it
+    /// can be switched back in without a jump in its state.
+    pub fn set_bypass(&self, bypassed: bool) -> Result<(), JsValue> {
+        self.post(Command::Bypass(bypassed))
+    }
+
+    /// Sets the length of the wrapper's fades, in frames.
+    ///
+    /// Applies to the fade-in when the processor starts, bypass crossfades and the fade-out
+    /// after the node is dropped (unless [`Processor::fade_out_time`] is set). Defaults to
+    /// 128 frames; `0` disables fading.
+    pub fn set_fade_length(&self, frames: usize) -> Result<(), JsValue> {
+        self.post(Command::FadeLength(frames))
+    }
+
+    fn post(&self, command: Command<P::Message>) -> Result<(), JsValue> {
+        self.messages.borrow_mut().push(command).map_err(|_| {
+            JsValue::from_str("Failed to send message: the processor's message queue is full")
+        })
+    }
+
+    /// Sets a callback that receives each event posted by the processor on the main thread.
+    ///
+    /// Replaces any previous callback. Events posted before the callback was set are
+    /// delivered immediately.
+    pub fn set_on_event(&self, callback: impl FnMut(P::Event) + 'static) {
+        self.inbox.on_event.replace(Some(Box::new(callback)));
+        self.inbox.dispatch_events();
+    }
+
+    /// Receives the next event posted by the processor, if any.
+    ///
+    /// Useful for polling events (e.g. from an animation frame) instead of setting a callback.
+    pub fn try_recv(&self) -> Option<P::Event> {
+        self.inbox.events.try_recv()
+    }
+
+    /// Sets a callback that receives the error if the processor fails.
+    ///
+    /// The callback is called at most once. If the processor already failed, it is called
+    /// immediately. A panic only stops this node's processor, which outputs silence from then
+    /// on, see [`ProcessorError`].
+    pub fn set_on_error(&self, callback: impl FnMut(ProcessorError) + 'static) {
+        self.inbox.on_error.replace(Some(Box::new(callback)));
+        self.inbox.dispatch_error();
+    }
+
+    /// Returns the error that stopped the processor, if it has failed.
+    pub fn error(&self) -> Option<ProcessorError> {
+        self.inbox.error.get()
+    }
+
+    /// Returns the latency reported by [`Processor::latency`], in frames.
+    ///
+    /// The latency is updated after each block, so it is `0` until the processor has rendered.
+    pub fn latency(&self) -> usize {
+        self.inbox.latency.load(Ordering::Acquire)
+    }
+
+    /// Sets a callback that receives the processor's latency, in frames, whenever it changes.
+    ///
+    /// Replaces any previous callback. If the latency has changed since it was last reported,
+    /// the callback is called immediately. The processor doesn't post latency changes, so they
+    /// are polled on the main thread, every 50 ms from the first call on.
+    pub fn set_on_latency_change(&self, callback: impl FnMut(usize) + 'static) {
+        self.inbox
+            .on_latency_change
+            .replace(Some(Box::new(callback)));
+        self.inbox.dispatch_latency();
+
+        if self.inbox.latency_poll.get().is_none() {
+            let weak = Rc::downgrade(&self.inbox);
+            let poll = Notification::new(move || {
+                if let Some(inbox) = Weak::upgrade(&weak) {
+                    inbox.dispatch_latency();
+                }
+            });
+            let handle = set_interval(poll.as_ref().unchecked_ref(), LATENCY_POLL_INTERVAL);
+            self.inbox.latency_poll.set(Some(handle));
+            self.inbox.notifications.borrow_mut().push(poll);
+        }
+    }
+
     /// Consumes the wrapper and returns the underlying AudioWorkletNode.
     ///
     /// Note: This will prevent the Drop implementation from running, so the processor
@@ -38,7 +362,7 @@ impl AudioWorkletNodeWrapper {
     }
 }
 
-impl Deref for AudioWorkletNodeWrapper {
+impl<P: Processor> Deref for AudioWorkletNodeWrapper<P> {
     type Target = AudioWorkletNode;
 
     fn deref(&self) -> &Self::Target {
@@ -46,17 +370,19 @@ impl Deref for AudioWorkletNodeWrapper {
     }
 }
 
-impl Drop for AudioWorkletNodeWrapper {
+impl<P: Processor> Drop for AudioWorkletNodeWrapper<P> {
     fn drop(&mut self) {
         self.is_active.store(false, Ordering::Release);
     }
 }
 
-impl Clone for AudioWorkletNodeWrapper {
+impl<P: Processor> Clone for AudioWorkletNodeWrapper<P> {
     fn clone(&self) -> Self {
         Self {
             node: self.node.clone(),
             is_active: self.is_active.clone(),
+            messages: self.messages.clone(),
+            inbox: self.inbox.clone(),
         }
     }
 }
@@ -1,10 +1,16 @@
-use js_sys::{Object, Reflect};
+use crate::buffer::ParameterValuesRef;
+use crate::mapping::ParameterMapping;
+use crate::smoothing::Smoothing;
+use js_sys::{Array, Object, Reflect};
 use wasm_bindgen::JsValue;
 
 /// Parameter descriptor for Web Audio API
 ///
 /// The `ParameterDescriptor` struct is used to specify properties for an AudioParam object
 /// that is used in an AudioWorkletNode, following the AudioParamDescriptor dictionary specification.
+///
+/// It also carries display metadata for user interfaces, which the browser does not use: see
+/// [`ParameterDescriptor::metadata`]. Unset fields can be filled with `..Default::default()`.
 #[derive(Debug, Clone)]
 pub struct ParameterDescriptor {
     /// The name of the parameter.
@@ -17,6 +23,82 @@ pub struct ParameterDescriptor {
     pub max_value: f32,
     /// The rate at which the parameter is automated (ARate or KRate).
     pub automation_rate: AutomationRate,
+    /// Smoothing applied to the values before they are handed to the processor, when they
+    /// are constant for the block. A-rate automation is passed through unsmoothed.
+    pub smoothing: Smoothing,
+    /// Human-readable name, e.g. `"Cutoff frequency"`. User interfaces fall back to `name`.
+    pub label: Option<String>,
+    /// Unit of the values, e.g. `"Hz"`, `"dB"` or `"ms"`.
+    pub unit: Option<String>,
+    /// How the range should be laid out on a control.
+    pub scale: ParameterScale,
+    /// Distance between the values a control should allow, for quantized parameters.
+    pub step: Option<f32>,
+    /// Names of the values of a discrete parameter: value `i` selects `choices[i]`.
+    pub choices: Vec<String>,
+    /// How the range maps to normalized `0..1` values, see [`ParameterDescriptor::to_normalized`].
+    pub mapping: ParameterMapping,
+}
+
+impl Default for ParameterDescriptor {
+    /// A parameter with the default range of an `AudioParamDescriptor` and no metadata.
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            default_value: 0.0,
+            min_value: f32::MIN,
+            max_value: f32::MAX,
+            automation_rate: AutomationRate::default(),
+            smoothing: Smoothing::None,
+            label: None,
+            unit: None,
+            scale: ParameterScale::default(),
+            step: None,
+            choices: Vec::new(),
+            mapping: ParameterMapping::default(),
+        }
+    }
+}
+
+impl ParameterDescriptor {
+    /// Converts a value of the parameter to a normalized value in `0..=1`, following its
+    /// [`mapping`](ParameterDescriptor::mapping).
+    ///
+    /// Does not allocate, so processors can keep their descriptors, e.g. from
+    /// [`Processor::prepare`](crate::Processor::prepare), and convert while rendering.
+    pub fn to_normalized(&self, value: f32) -> f32 {
+        self.mapping
+            .to_normalized(value, self.min_value, self.max_value)
+    }
+
+    /// Converts a normalized value in `0..=1` to a value of the parameter, following its
+    /// [`mapping`](ParameterDescriptor::mapping).
+    pub fn from_normalized(&self, normalized: f32) -> f32 {
+        self.mapping
+            .from_normalized(normalized, self.min_value, self.max_value)
+    }
+
+    /// Returns the descriptor with its display metadata as a JS object, for auto-generated
+    /// user interfaces on the main thread.
+    ///
+    /// Holds the standard `AudioParamDescriptor` fields, `label` (the name if unset), `scale`
+    /// (`"linear"`, `"logarithmic"` or `"exponential"`) and `choices` (empty for continuous
+    /// parameters), plus `unit` and `step` when they are set.
+    pub fn metadata(&self) -> Object {
+        let obj: Object = JsValue::from(self.clone()).into();
+        let label = self.label.as_deref().unwrap_or(&self.name);
+        Reflect::set(&obj, &"label".into(), &label.into()).unwrap();
+        if let Some(unit) = &self.unit {
+            Reflect::set(&obj, &"unit".into(), &unit.into()).unwrap();
+        }
+        Reflect::set(&obj, &"scale".into(), &self.scale.into()).unwrap();
+        if let Some(step) = self.step {
+            Reflect::set(&obj, &"step".into(), &step.into()).unwrap();
+        }
+        let choices: Array = self.choices.iter().map(JsValue::from).collect();
+        Reflect::set(&obj, &"choices".into(), &choices).unwrap();
+        obj
+    }
 }
 
 impl From<ParameterDescriptor> for JsValue {
@@ -31,14 +113,58 @@ impl From<ParameterDescriptor> for JsValue {
     }
 }
 
+/// Checks that parameter descriptors can be registered with the browser.
+///
+/// Fails with a message naming the offending parameter if a name is empty or used twice, a value
+/// is NaN or infinite, `min_value` is greater than `max_value`, or `default_value` is outside the
+/// range. Processors are validated before they are registered, and by
+/// [`RenderHarness`](crate::RenderHarness).
+pub fn validate_descriptors(descriptors: &[ParameterDescriptor]) -> Result<(), String> {
+    for (index, descriptor) in descriptors.iter().enumerate() {
+        let name = &descriptor.name;
+        if name.is_empty() {
+            return Err(format!("parameter {index} has an empty name"));
+        }
+        if descriptors[..index].iter().any(|other| other.name == *name) {
+            return Err(format!("parameter `{name}` is declared more than once"));
+        }
+
+        let values = [
+            ("defaultValue", descriptor.default_value),
+            ("minValue", descriptor.min_value),
+            ("maxValue", descriptor.max_value),
+        ];
+        if let Some((field, value)) = values.iter().find(|(_, value)| !value.is_finite()) {
+            return Err(format!(
+                "parameter `{name}` has a non-finite {field} ({value})"
+            ));
+        }
+
+        let (min, max) = (descriptor.min_value, descriptor.max_value);
+        if min > max {
+            return Err(format!(
+                "parameter `{name}` has a minValue ({min}) greater than its maxValue ({max})"
+            ));
+        }
+        let default = descriptor.default_value;
+        if default < min || default > max {
+            return Err(format!(
+                "parameter `{name}` has a defaultValue ({default}) outside its range ({min} to {max})"
+            ));
+        }
+    }
+    Ok(())
+}
+
 /// The automation rate of an AudioParam.
 ///
 /// The automation rate can be selected by setting the `automationRate` attribute
 /// with one of the following values. Some AudioParams may have constraints on
 /// whether the automation rate can be changed.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum AutomationRate {
     /// Audio-rate automation. The parameter is updated for every sample frame.
+    #[default]
     ARate,
     /// Control-rate automation. The parameter is updated for every render quantum.
     KRate,
@@ -52,3 +178,190 @@ impl From<AutomationRate> for JsValue {
         }
     }
 }
+
+/// How a parameter's range is laid out on a control, e.g. a slider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParameterScale {
+    /// Values are spread evenly.
+    #[default]
+    Linear,
+    /// Each octave or decade takes the same space, e.g. for frequencies.
+    Logarithmic,
+    /// Small values take more space, e.g. for times.
+    Exponential,
+}
+
+impl ParameterScale {
+    /// Returns the name of the scale in the JS metadata.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParameterScale::Linear => "linear",
+            ParameterScale::Logarithmic => "logarithmic",
+            ParameterScale::Exponential => "exponential",
+        }
+    }
+}
+
+impl From<ParameterScale> for JsValue {
+    fn from(val: ParameterScale) -> Self {
+        val.as_str().into()
+    }
+}
+
+/// A set of parameters with typed, index-based access.
+///
+/// Usually derived with `#[derive(Parameters)]` on a struct with one `&[f32]` field per parameter,
+/// or one [`ParameterValue`](crate::ParameterValue) field to know when the value is constant.
+/// The derive generates the [`ParameterDescriptor`] list from the `#[param(...)]` attributes and
+/// fills each field by index, so parameter names are checked at compile time.
+///
+/// Options for `#[param(...)]`, all optional:
+/// - `name`: parameter name, defaults to the field name
+/// - `default`, `min`, `max`: default, minimum and maximum value
+/// - `automation_rate`: `"a-rate"` (default) or `"k-rate"`
+/// - `smoothing`: `"linear"`, `"exponential"` or `"multiplicative"`, see [`Smoothing`]
+/// - `smoothing_time`: length of the smoothing in seconds, defaults to `0.02`
+/// - `label`, `unit`: human-readable name and unit, e.g. `unit = "Hz"`
+/// - `scale`: `"linear"` (default), `"logarithmic"` or `"exponential"`, see [`ParameterScale`]
+/// - `step`: distance between the values a control should allow
+/// - `choices("a", "b", ...)`: names of the values of a discrete parameter; the range defaults to
+///   `0` to the last index, with a step of `1`
+/// - `mapping`: `"linear"`, `"logarithmic"`, `"skewed"` (with `skew`), `"decibels"` (with
+///   `min_db`, defaults to `-60.0`) or `"stepped"`, see [`ParameterMapping`]; defaults to
+///   `"stepped"` with a `step`, `"logarithmic"` on a logarithmic `scale` and `"linear"` otherwise
+///
+/// # Example
+/// ```ignore
+/// #[derive(Parameters)]
+/// pub struct FilterParams<'a> {
+///     #[param(default = 1000.0, min = 20.0, max = 20000.0, smoothing = "multiplicative")]
+///     #[param(label = "Cutoff", unit = "Hz", scale = "logarithmic")]
+///     pub cutoff: &'a [f32],
+///     #[param(default = 1.0, min = 0.1, max = 30.0, automation_rate = "k-rate")]
+///     pub resonance: &'a [f32],
+/// }
+///
+/// impl Processor for FilterProcessor {
+///     type Params = FilterParams<'static>;
+///
+///     fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]], context: &ProcessContext, params: &FilterParams) {
+///         let cutoff = params.cutoff;
+///         // ...
+///     }
+/// }
+/// ```
+pub trait Parameters {
+    /// The accessor handed to [`Processor::process`](crate::Processor::process) for each block.
+    type Values<'a>;
+
+    /// Returns the descriptors of the parameters, in index order.
+    fn descriptors() -> Vec<ParameterDescriptor>;
+
+    /// Builds the accessor from the parameter buffers of the current block.
+    fn values(params: ParameterValuesRef<'_>) -> Self::Values<'_>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn descriptor(
+        name: &str,
+        default_value: f32,
+        min_value: f32,
+        max_value: f32,
+    ) -> ParameterDescriptor {
+        ParameterDescriptor {
+            name: name.to_string(),
+            default_value,
+            min_value,
+            max_value,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn valid_descriptors_pass() {
+        let descriptors = [
+            descriptor("cutoff", 1000.0, 20.0, 20000.0),
+            descriptor("gain", 0.0, 0.0, 0.0),
+            ParameterDescriptor {
+                name: "unbounded".to_string(),
+                ..Default::default()
+            },
+        ];
+        assert_eq!(validate_descriptors(&descriptors), Ok(()));
+        assert_eq!(validate_descriptors(&[]), Ok(()));
+    }
+
+    #[test]
+    fn names_must_be_unique_and_not_empty() {
+        let duplicate = [
+            descriptor("gain", 0.5, 0.0, 1.0),
+            descriptor("gain", 0.5, 0.0, 1.0),
+        ];
+        assert_eq!(
+            validate_descriptors(&duplicate),
+            Err("parameter `gain` is declared more than once".to_string())
+        );
+
+        let empty = [
+            descriptor("gain", 0.5, 0.0, 1.0),
+            descriptor("", 0.5, 0.0, 1.0),
+        ];
+        assert_eq!(
+            validate_descriptors(&empty),
+            Err("parameter 1 has an empty name".to_string())
+        );
+    }
+
+    #[test]
+    fn values_must_be_finite() {
+        assert_eq!(
+            validate_descriptors(&[descriptor("gain", f32::NAN, 0.0, 1.0)]),
+            Err("parameter `gain` has a non-finite defaultValue (NaN)".to_string())
+        );
+        assert_eq!(
+            validate_descriptors(&[descriptor("gain", 0.5, f32::NAN, 1.0)]),
+            Err("parameter `gain` has a non-finite minValue (NaN)".to_string())
+        );
+        assert_eq!(
+            validate_descriptors(&[descriptor("gain", 0.5, 0.0, f32::NAN)]),
+            Err("parameter `gain` has a non-finite maxValue (NaN)".to_string())
+        );
+        assert_eq!(
+            validate_descriptors(&[descriptor("gain", 0.5, f32::NEG_INFINITY, 1.0)]),
+            Err("parameter `gain` has a non-finite minValue (-inf)".to_string())
+        );
+        assert_eq!(
+            validate_descriptors(&[descriptor("gain", 0.5, 0.0, f32::INFINITY)]),
+            Err("parameter `gain` has a non-finite maxValue (inf)".to_string())
+        );
+    }
+
+    #[test]
+    fn range_must_not_be_inverted() {
+        assert_eq!(
+            validate_descriptors(&[descriptor("q", 1.0, 30.0, 0.1)]),
+            Err("parameter `q` has a minValue (30) greater than its maxValue (0.1)".to_string())
+        );
+    }
+
+    #[test]
+    fn default_must_be_in_range() {
+        assert_eq!(
+            validate_descriptors(&[descriptor("cutoff", 10.0, 20.0, 20000.0)]),
+            Err(
+                "parameter `cutoff` has a defaultValue (10) outside its range (20 to 20000)"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            validate_descriptors(&[descriptor("cutoff", 30000.0, 20.0, 20000.0)]),
+            Err(
+                "parameter `cutoff` has a defaultValue (30000) outside its range (20 to 20000)"
+                    .to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use std::mem;
+
+/// Audio channels grouped by the node input or output they belong to.
+///
+/// Each port holds the channels the browser provided for it in the current block,
+/// so a disconnected input has no channels rather than shifting the channels of later inputs.
+///
+/// # Example
+/// ```ignore
+/// // Sidechain compressor: input 0 is the signal, input 1 is the sidechain.
+/// let signal = inputs.port(0).unwrap_or_default();
+/// let sidechain = inputs.port(1).unwrap_or_default();
+/// ```
+pub struct Ports<'a, T> {
+    channels: &'a mut [T],
+    layout: &'a [usize],
+}
+
+impl<'a, T> Ports<'a, T> {
+    /// Creates a new `Ports` view, where `layout` holds the number of channels in each port.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the layout does not add up to the number of channels.
+    pub fn new(channels: &'a mut [T], layout: &'a [usize]) -> Self {
+        assert_eq!(
+            layout.iter().sum::<usize>(),
+            channels.len(),
+            "Port layout does not match the number of channels"
+        );
+        Self { channels, layout }
+    }
+
+    /// Returns the number of ports.
+    pub fn len(&self) -> usize {
+        self.layout.len()
+    }
+
+    /// Returns `true` if there are no ports.
+    pub fn is_empty(&self) -> bool {
+        self.layout.is_empty()
+    }
+
+    /// Returns the channels of the port at `index`, or `None` if there is no such port.
+    pub fn port(&self, index: usize) -> Option<&[T]> {
+        let range = self.range(index)?;
+        Some(&self.channels[range])
+    }
+
+    /// Returns the channels of the port at `index` mutably, or `None` if there is no such port.
+    pub fn port_mut(&mut self, index: usize) -> Option<&mut [T]> {
+        let range = self.range(index)?;
+        Some(&mut self.channels[range])
+    }
+
+    /// Returns the channels of every port, flattened in port order.
+    pub fn channels(&self) -> &[T] {
+        self.channels
+    }
+
+    /// Returns the channels of every port mutably, flattened in port order.
+    pub fn channels_mut(&mut self) -> &mut [T] {
+        self.channels
+    }
+
+    /// Iterates over the channels of each port.
+    pub fn iter(&self) -> impl Iterator<Item = &[T]> + '_ {
+        let mut rest = &*self.channels;
+        self.layout.iter().map(move |&num_channels| {
+            let (port, tail) = rest.split_at(num_channels);
+            rest = tail;
+            port
+        })
+    }
+
+    /// Iterates mutably over the channels of each port.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut [T]> + '_ {
+        let mut rest = &mut *self.channels;
+        self.layout.iter().map(move |&num_channels| {
+            let (port, tail) = mem::take(&mut rest).split_at_mut(num_channels);
+            rest = tail;
+            port
+        })
+    }
+
+    fn range(&self, index: usize) -> Option<std::ops::Range<usize>> {
+        let num_channels = *self.layout.get(index)?;
+        let start = self.layout[..index].iter().sum::<usize>();
+        Some(start..start + num_channels)
+    }
+}
@@ -1,4 +1,40 @@
-use crate::{buffer::ParameterValuesRef, parameter::ParameterDescriptor};
+use crate::{
+    buffer::ParameterValuesRef,
+    config::NodeConfig,
+    parameter::{ParameterDescriptor, Parameters},
+    port::Ports,
+};
+
+/// Timing information for the block being rendered, passed to [`Processor::process`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessContext {
+    /// Sample rate of the audio context, in Hz.
+    pub sample_rate: f32,
+    /// Frame position of the first sample of the block (`currentFrame`).
+    pub current_frame: u64,
+    /// Time of the first sample of the block, in seconds (`currentTime`).
+    pub current_time: f64,
+    /// Number of frames in the block.
+    pub block_size: usize,
+}
+
+impl ProcessContext {
+    /// Creates a context for the block starting at `current_frame`, deriving `current_time`
+    /// from the sample rate.
+    pub fn new(sample_rate: f32, current_frame: u64, block_size: usize) -> Self {
+        Self {
+            sample_rate,
+            current_frame,
+            current_time: current_frame as f64 / sample_rate as f64,
+            block_size,
+        }
+    }
+
+    /// Returns the time in seconds of the frame at `offset` within the block.
+    pub fn time_at(&self, offset: usize) -> f64 {
+        self.current_time + offset as f64 / self.sample_rate as f64
+    }
+}
 
 /// The `Processor` trait defines the interface for audio processing units.
 pub trait Processor: 'static + Send {
@@ -7,26 +43,169 @@ pub trait Processor: 'static + Send {
     /// This type represents the configuration or state data required to construct and operate the processor.
     type Data: 'static + Send;
 
+    /// Parameters handed to [`Processor::process`].
+    ///
+    /// Defaults to untyped [`ParameterValuesRef`], looked up by name. Set this to a struct
+    /// deriving [`Parameters`] (e.g. `FilterParams<'static>`) for typed, index-based access.
+    type Params: Parameters = ParameterValuesRef<'static>;
+
+    /// Messages sent from the main thread with `AudioWorkletNodeWrapper::send`.
+    ///
+    /// Messages are passed through shared memory as Rust values and delivered to
+    /// [`Processor::on_message`] before the next block is rendered, or to
+    /// [`Processor::handle_event`] at a given frame when scheduled.
+    type Message: 'static + Send = ();
+
+    /// Events posted from the processor back to the main thread.
+    ///
+    /// Events are collected with [`Processor::poll_event`] after each block and delivered
+    /// to the callback set with `AudioWorkletNodeWrapper::set_on_event`.
+    type Event: 'static + Send = ();
+
     /// Creates a new instance of the processor with the given data.
     fn new(data: Self::Data) -> Self;
 
+    /// Optional: prepare for rendering.
+    ///
+    /// Called once before the first block with the context's sample rate and the largest number
+    /// of frames a single call to [`Processor::process`] will receive, the node's render quantum
+    /// size (see [`NodeConfig::render_quantum_size`](crate::NodeConfig::render_quantum_size)).
+    /// Allocate buffers here rather than in `process`. If the browser renders a larger block
+    /// than announced, this is called again with its size before the block is rendered.
+    #[allow(unused_variables)]
+    fn prepare(&mut self, sample_rate: f32, max_block_size: usize) {}
+
+    /// Optional: clear DSP state, e.g. delay lines and filter memory.
+    ///
+    /// Called before the next block when `AudioWorkletNodeWrapper::reset` is called, so the node
+    /// can be reused without being recreated.
+    fn reset(&mut self) {}
+
+    /// Optional: release resources when the node is deactivated.
+    ///
+    /// Called once when the processor stops rendering, after the node wrapper has been dropped
+    /// and the processor's tail has finished.
+    fn on_deactivate(&mut self) {}
+
+    /// Optional: the processor's latency, in frames.
+    ///
+    /// Report the delay introduced by lookahead or block-based processing, so parallel paths can
+    /// be aligned, e.g. with [`DelayCompensation`](crate::DelayCompensation). Checked after each
+    /// block; changes are reported through `AudioWorkletNodeWrapper::set_on_latency_change`.
+    fn latency(&self) -> usize {
+        0
+    }
+
+    /// Optional: how long the processor keeps producing output after its inputs go silent,
+    /// in seconds.
+    ///
+    /// After the node wrapper is dropped, the processor keeps rendering until its inputs have
+    /// been silent for this long, so reverb and delay tails are not cut off. Defaults to `0.0`.
+    fn tail_time(&self) -> f64 {
+        0.0
+    }
+
+    /// Optional: whether the processor is still producing output.
+    ///
+    /// Checked once the tail time has elapsed: return `true` to keep rendering, e.g. until a
+    /// reverb has decayed below a threshold.
+    fn is_ringing(&self) -> bool {
+        false
+    }
+
+    /// Optional: length of the fade-out applied once the tail has finished, in seconds.
+    ///
+    /// Avoids clicks when a processor that never goes silent on its own, such as an oscillator,
+    /// is stopped. Defaults to the node's fade length, see
+    /// `AudioWorkletNodeWrapper::set_fade_length`.
+    fn fade_out_time(&self) -> Option<f64> {
+        None
+    }
+
+    /// Optional: handle a message sent from the main thread.
+    #[allow(unused_variables)]
+    fn on_message(&mut self, message: Self::Message) {}
+
+    /// Optional: handle a message scheduled with `AudioWorkletNodeWrapper::schedule`.
+    ///
+    /// Called at the exact frame the message was scheduled for: the block is split at that frame,
+    /// so the frames after it are rendered by a separate call to [`Processor::process`].
+    /// Defaults to [`Processor::on_message`].
+    fn handle_event(&mut self, message: Self::Message) {
+        self.on_message(message);
+    }
+
+    /// Optional: return the next event to post to the main thread.
+    ///
+    /// Called after each block until it returns `None`.
+    fn poll_event(&mut self) -> Option<Self::Event> {
+        None
+    }
+
     /// Processes audio buffers.
     ///
+    /// The channels of all inputs and outputs are flattened in port order. Processors with
+    /// several inputs or outputs should implement [`Processor::process_ports`] instead, in which
+    /// case this method is not called.
+    ///
+    /// Every processor must implement one of the two: the default implementation renders
+    /// silence, and panics in debug builds as it is only reached when neither is implemented.
+    ///
     /// # Parameters
     /// - `inputs`: Input audio channels (may be empty for generators)
     /// - `outputs`: Output audio channels to fill
-    /// - `sample_rate`: Current audio context sample rate
-    /// - `params`: Parameter buffers - use `params.get("name")` to access 128-sample buffers
+    /// - `context`: Sample rate and position of the block being rendered
+    /// - `params`: Parameter buffers - use `params.get("name")` to access per-frame buffers,
+    ///   or the fields of [`Processor::Params`] when typed
+    #[allow(unused_variables)]
     fn process(
         &mut self,
         inputs: &[&[f32]],
         outputs: &mut [&mut [f32]],
-        sample_rate: f32,
-        params: &ParameterValuesRef,
-    );
+        context: &ProcessContext,
+        params: &<Self::Params as Parameters>::Values<'_>,
+    ) {
+        debug_assert!(
+            false,
+            "`{}` implements neither `Processor::process` nor `Processor::process_ports`",
+            std::any::type_name::<Self>()
+        );
+    }
+
+    /// Processes audio buffers grouped by node input and output.
+    ///
+    /// Each port holds the channels the browser provided for that input or output in this
+    /// block, so a disconnected input has no channels instead of shifting later inputs.
+    /// The default implementation flattens the ports and calls [`Processor::process`].
+    ///
+    /// # Parameters
+    /// - `inputs`: Input audio channels, grouped per node input
+    /// - `outputs`: Output audio channels to fill, grouped per node output
+    /// - `context`: Sample rate and position of the block being rendered
+    /// - `params`: Parameter buffers - use `params.get("name")` to access per-frame buffers,
+    ///   or the fields of [`Processor::Params`] when typed
+    fn process_ports(
+        &mut self,
+        inputs: &Ports<&[f32]>,
+        outputs: &mut Ports<&mut [f32]>,
+        context: &ProcessContext,
+        params: &<Self::Params as Parameters>::Values<'_>,
+    ) {
+        self.process(inputs.channels(), outputs.channels_mut(), context, params);
+    }
 
     /// Optional: return parameter descriptors
+    ///
+    /// Defaults to the descriptors of [`Processor::Params`].
     fn parameter_descriptors() -> Vec<ParameterDescriptor> {
-        Vec::new()
+        Self::Params::descriptors()
+    }
+
+    /// Optional: declare the node's ports and channel configuration
+    ///
+    /// Declared fields are applied by `create_node`, which rejects caller options that
+    /// conflict with them. Defaults to an empty configuration, leaving everything to the caller.
+    fn node_config() -> NodeConfig {
+        NodeConfig::default()
     }
 }
@@ -1,13 +1,27 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::future;
+use std::ptr;
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+    atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering},
+    Arc, OnceLock,
 };
+use std::task::{Poll, Waker};
 
+use crate::buffer::RENDER_QUANTUM_SIZE;
+use crate::config::NodeConfig;
+use crate::error::ErrorSlot;
+use crate::message::{command_channel, event_channel};
 use crate::node::AudioWorkletNodeWrapper;
+use crate::parameter::{validate_descriptors, ParameterDescriptor};
 use crate::processor::Processor;
 use crate::wrapper::{ProcessorWrapper, ProcessorWrapperData};
+use js_sys::{Array, Object, Reflect, WeakMap, WeakSet};
 use wasm_bindgen::prelude::*;
-use web_sys::AudioContext;
+use web_sys::{
+    AudioWorkletGlobalScope, AudioWorkletNodeOptions, AudioWorkletProcessor, BaseAudioContext,
+};
+use web_thread::web::audio_worklet::{AudioWorkletGlobalScopeExt, ExtendAudioWorkletProcessor};
 
 /// Registration entry for inventory
 pub struct ProcessorRegistration {
@@ -15,76 +29,550 @@ pub struct ProcessorRegistration {
     pub name: &'static str,
     /// The function used to register the processor
     pub register_fn: fn() -> Result<(), JsValue>,
+    /// Returns the processor's parameter descriptors, validated before registering
+    pub parameter_descriptors: fn() -> Vec<ParameterDescriptor>,
 }
 
 impl ProcessorRegistration {
-    /// Creates a new `ProcessorRegistration` with the given name and registration function.
-    pub const fn new(name: &'static str, register_fn: fn() -> Result<(), JsValue>) -> Self {
-        Self { name, register_fn }
+    /// Creates a new `ProcessorRegistration` with the given name, registration function and
+    /// parameter descriptors.
+    pub const fn new(
+        name: &'static str,
+        register_fn: fn() -> Result<(), JsValue>,
+        parameter_descriptors: fn() -> Vec<ParameterDescriptor>,
+    ) -> Self {
+        Self {
+            name,
+            register_fn,
+            parameter_descriptors,
+        }
     }
 }
 
 // Collect all registrations using inventory
 inventory::collect!(ProcessorRegistration);
 
-/// Register all processors in the given audio context
-pub async fn register_all(ctx: &AudioContext) -> Result<(), JsValue> {
-    use web_thread::web::audio_worklet::BaseAudioContextExt;
+/// Why a processor could not be registered.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistrationError {
+    /// Another processor was submitted under the same name; only the first one is registered.
+    Duplicate,
+    /// No processor was submitted under the requested name.
+    Unknown,
+    /// The processor's parameter descriptors are invalid, with the reason; see
+    /// [`validate_descriptors`].
+    InvalidParameters(String),
+    /// `registerProcessor` failed in the worklet, with the error it reported.
+    Failed(String),
+}
+
+impl fmt::Display for RegistrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistrationError::Duplicate => write!(f, "duplicate processor name"),
+            RegistrationError::Unknown => write!(f, "no processor registered with this name"),
+            RegistrationError::InvalidParameters(error) => {
+                write!(f, "invalid parameter descriptors: {error}")
+            }
+            RegistrationError::Failed(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+/// A processor that could not be registered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegistrationFailure {
+    /// The name the processor was submitted or requested under
+    pub name: String,
+    /// Why it could not be registered
+    pub error: RegistrationError,
+}
+
+/// Outcome of [`register_all`] and [`register`]: which processors were registered and which failed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RegistrationReport {
+    /// Names of the processors that were registered
+    pub registered: Vec<&'static str>,
+    /// Names of the processors that were skipped, as an earlier call already registered them
+    pub already_registered: Vec<&'static str>,
+    /// Processors that could not be registered
+    pub failed: Vec<RegistrationFailure>,
+}
+
+impl RegistrationReport {
+    /// Returns `true` if every processor was registered.
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Returns the registered names, or an error listing every failure.
+    ///
+    /// Useful to fail fast at startup:
+    /// ```ignore
+    /// waw::register_all(&ctx).await?.into_result()?;
+    /// ```
+    pub fn into_result(self) -> Result<Vec<&'static str>, JsValue> {
+        if self.is_ok() {
+            return Ok(self.registered);
+        }
+
+        let failures: Vec<_> = self
+            .failed
+            .iter()
+            .map(|failure| format!("{}: {}", failure.name, failure.error))
+            .collect();
+        Err(js_sys::Error::new(&format!(
+            "Failed to register processors: {}",
+            failures.join("; ")
+        ))
+        .into())
+    }
+}
+
+/// A value handed from the worklet thread to a future awaiting it on the main thread.
+///
+/// Neither side locks, as the main thread is not allowed to block on atomics.
+struct Completion<T> {
+    value: OnceLock<T>,
+    waker: AtomicPtr<Waker>,
+}
 
-    let completed = Arc::new(AtomicBool::new(false));
-    let completed_clone = completed.clone();
-
-    // Clone registrations for move into closure
-    let registrations: Vec<_> = inventory::iter::<ProcessorRegistration>()
-        .map(|reg| ProcessorRegistration::new(reg.name, reg.register_fn))
-        .collect();
-
-    ctx.clone()
-        .register_thread(None, move || {
-            for reg in &registrations {
-                if let Err(e) = (reg.register_fn)() {
-                    web_sys::console::error_1(
-                        &format!("Failed to register {}: {:?}", reg.name, e).into(),
-                    );
-                }
+impl<T> Completion<T> {
+    fn new() -> Self {
+        Self {
+            value: OnceLock::new(),
+            waker: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Stores the value and wakes the waiting future.
+    fn complete(&self, value: T) {
+        let _ = self.value.set(value);
+        let waker = self.waker.swap(ptr::null_mut(), Ordering::AcqRel);
+        if !waker.is_null() {
+            // SAFETY: Non-null pointers are created by `Box::into_raw` in `wait`, and whoever
+            // swaps them out takes ownership.
+            unsafe { Box::from_raw(waker) }.wake();
+        }
+    }
+
+    /// Waits until the value has been stored.
+    async fn wait(&self) -> &T {
+        future::poll_fn(|cx| {
+            // Register the waker before checking, so a concurrent `complete` either sees it
+            // or stored the value before we check.
+            let waker = Box::into_raw(Box::new(cx.waker().clone()));
+            let previous = self.waker.swap(waker, Ordering::AcqRel);
+            if !previous.is_null() {
+                // SAFETY: See `complete`.
+                drop(unsafe { Box::from_raw(previous) });
             }
 
-            completed_clone.store(true, Ordering::Release);
+            match self.value.get() {
+                Some(value) => Poll::Ready(value),
+                None => Poll::Pending,
+            }
         })
         .await
-        .map_err(|e| JsValue::from_str(&format!("Failed to register thread: {:?}", e)))?;
+    }
+}
+
+impl<T> Drop for Completion<T> {
+    fn drop(&mut self) {
+        let waker = *self.waker.get_mut();
+        if !waker.is_null() {
+            // SAFETY: See `complete`.
+            drop(unsafe { Box::from_raw(waker) });
+        }
+    }
+}
+
+/// A processor registered with [`register!`](crate::register), identified by its name.
+///
+/// Implemented by the `register!` macro; use it to register processors by type:
+/// ```ignore
+/// waw::registry::register(&ctx, &[FilterProcessor::NAME, OscillatorProcessor::NAME]).await?;
+/// ```
+pub trait Registered: Processor {
+    /// The name the processor is registered under.
+    const NAME: &'static str;
+}
+
+thread_local! {
+    /// Names of the processors registered on each context, as a JS array keyed by the context.
+    /// Held weakly, so closed contexts can still be collected. Contexts are added once their
+    /// worklet thread has started.
+    static REGISTERED: WeakMap = WeakMap::new();
+
+    /// Contexts with a registration in progress.
+    static PENDING: WeakSet = WeakSet::new();
+}
+
+/// Name of the processor that registers processors after the worklet thread has started.
+const REGISTRAR_NAME: &str = "waw-registrar";
+
+/// Runs a registration on a context's worklet thread, from the constructor of a node created
+/// for it.
+///
+/// The worklet thread can only be started once per context, with the first registration;
+/// later ones go through a node of this processor, which stops right away.
+struct Registrar;
+
+impl ExtendAudioWorkletProcessor for Registrar {
+    type Data = Box<dyn FnOnce() + Send>;
+
+    fn new(
+        _this: AudioWorkletProcessor,
+        data: Option<Self::Data>,
+        _options: AudioWorkletNodeOptions,
+    ) -> Self {
+        if let Some(register) = data {
+            register();
+        }
+        Registrar
+    }
 
-    while !completed.load(Ordering::Acquire) {
-        web_thread::web::yield_now_async(web_thread::web::YieldTime::UserBlocking).await;
+    fn process(&mut self, _inputs: Array, _outputs: Array, _parameters: Object) -> bool {
+        false
     }
-    web_thread::web::yield_now_async(web_thread::web::YieldTime::UserBlocking).await;
+}
 
-    Ok(())
+/// Marks a registration in progress on a context, until dropped.
+struct PendingRegistration<'a>(&'a BaseAudioContext);
+
+impl<'a> PendingRegistration<'a> {
+    /// Marks a registration in progress, or returns `None` if one already is.
+    fn claim(ctx: &'a BaseAudioContext) -> Option<Self> {
+        PENDING.with(|pending| {
+            if pending.has(ctx) {
+                return None;
+            }
+            pending.add(ctx);
+            Some(Self(ctx))
+        })
+    }
+}
+
+impl Drop for PendingRegistration<'_> {
+    fn drop(&mut self) {
+        PENDING.with(|pending| pending.delete(self.0));
+    }
+}
+
+/// Register all processors in the given audio context
+///
+/// Works with any `BaseAudioContext`, including an `OfflineAudioContext`.
+/// See [`register`] for details.
+pub async fn register_all(ctx: &BaseAudioContext) -> Result<RegistrationReport, JsValue> {
+    register_matching(ctx, |_| true).await
+}
+
+/// Register the processors with the given names in the given audio context
+///
+/// Names that no processor was submitted under are reported as [`RegistrationError::Unknown`].
+/// Processors can be registered incrementally: those an earlier call registered on the context
+/// are skipped and listed in [`RegistrationReport::already_registered`]. Calls on the same
+/// context must not overlap; await each one before the next. Processors submitted under a
+/// name that was already taken are not registered, and are reported as
+/// [`RegistrationError::Duplicate`] until the name has been registered; later calls only list
+/// the name in [`RegistrationReport::already_registered`]. The returned error is only for failing to reach the worklet thread; check the
+/// report for processors that failed.
+pub async fn register(
+    ctx: &BaseAudioContext,
+    names: &[&str],
+) -> Result<RegistrationReport, JsValue> {
+    let mut report = register_matching(ctx, |name| names.contains(&name)).await?;
+
+    for &name in names {
+        let known = inventory::iter::<ProcessorRegistration>().any(|reg| reg.name == name);
+        if !known {
+            report.failed.push(RegistrationFailure {
+                name: name.to_string(),
+                error: RegistrationError::Unknown,
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+/// Returns the names of the processors registered on the given audio context.
+pub fn registered_processors(ctx: &BaseAudioContext) -> Vec<String> {
+    REGISTERED.with(|registered| match registered.get(ctx).dyn_into::<Array>() {
+        Ok(names) => names.iter().filter_map(|name| name.as_string()).collect(),
+        Err(_) => Vec::new(),
+    })
+}
+
+/// Returns `true` if a processor with the given name is registered on the given audio context.
+pub fn is_registered(ctx: &BaseAudioContext, name: &str) -> bool {
+    registered_processors(ctx)
+        .iter()
+        .any(|registered| registered == name)
+}
+
+async fn register_matching(
+    ctx: &BaseAudioContext,
+    filter: impl Fn(&str) -> bool,
+) -> Result<RegistrationReport, JsValue> {
+    use web_thread::web::audio_worklet::BaseAudioContextExt;
+
+    let Some(_pending) = PendingRegistration::claim(ctx) else {
+        return Err(JsValue::from_str(
+            "Processors are already being registered on this context",
+        ));
+    };
+
+    let started = REGISTERED.with(|registered| registered.has(ctx));
+    let already_registered = registered_processors(ctx);
+    let mut report = RegistrationReport::default();
+
+    // Keep the first registration of each name, if it is missing and its parameters can be
+    // registered
+    let mut names = HashSet::new();
+    let mut registrations = Vec::new();
+    for reg in inventory::iter::<ProcessorRegistration>().filter(|reg| filter(reg.name)) {
+        let registered = already_registered.iter().any(|name| name == reg.name);
+        if !names.insert(reg.name) {
+            // Duplicates were reported by the call that registered the name
+            if !registered {
+                report.failed.push(RegistrationFailure {
+                    name: reg.name.to_string(),
+                    error: RegistrationError::Duplicate,
+                });
+            }
+        } else if registered {
+            report.already_registered.push(reg.name);
+        } else if let Err(error) = validate_descriptors(&(reg.parameter_descriptors)()) {
+            report.failed.push(RegistrationFailure {
+                name: reg.name.to_string(),
+                error: RegistrationError::InvalidParameters(error),
+            });
+        } else {
+            registrations.push(ProcessorRegistration::new(
+                reg.name,
+                reg.register_fn,
+                reg.parameter_descriptors,
+            ));
+        }
+    }
+
+    if started && registrations.is_empty() {
+        return Ok(report);
+    }
+
+    let completion = Arc::new(Completion::new());
+    let worklet_completion = completion.clone();
+    let register = move || {
+        // `JsValue`s can't leave the worklet thread, so errors are passed as messages
+        let results: Vec<_> = registrations
+            .iter()
+            .map(|reg| {
+                let result = (reg.register_fn)()
+                    .map_err(|e| e.as_string().unwrap_or_else(|| format!("{:?}", e)));
+                (reg.name, result)
+            })
+            .collect();
+
+        worklet_completion.complete(results);
+    };
+
+    let registrar = if started {
+        let node = ctx
+            .audio_worklet_node::<Registrar>(REGISTRAR_NAME, Box::new(register), None)
+            .map_err(|e| {
+                JsValue::from_str(&format!("Failed to reach the worklet thread: {:?}", e))
+            })?;
+        Some(node)
+    } else {
+        ctx.clone()
+            .register_thread(None, move || {
+                // Later calls register their processors through the registrar. If this fails,
+                // they report that they can't reach the worklet thread.
+                let global: AudioWorkletGlobalScope = js_sys::global().unchecked_into();
+                let _ = global.register_processor_ext::<Registrar>(REGISTRAR_NAME);
+                register();
+            })
+            .await
+            .map_err(|e| JsValue::from_str(&format!("Failed to register thread: {:?}", e)))?;
+        None
+    };
+
+    for (name, result) in completion.wait().await {
+        match result {
+            Ok(()) => report.registered.push(*name),
+            Err(error) => report.failed.push(RegistrationFailure {
+                name: name.to_string(),
+                error: RegistrationError::Failed(error.clone()),
+            }),
+        }
+    }
+
+    // The registrar node is kept alive until it has run
+    drop(registrar);
+
+    REGISTERED.with(|map| {
+        let registered: Array = map.get(ctx).dyn_into().unwrap_or_default();
+        for &name in &report.registered {
+            registered.push(&JsValue::from(name));
+        }
+        map.set(ctx, &registered);
+    });
+
+    Ok(report)
 }
 
 /// Create an audio worklet node
+///
+/// Works with any `BaseAudioContext`, including an `OfflineAudioContext`. Fails if no processor
+/// named `name` was registered on the context.
+///
+/// Buffers are allocated for the context's render quantum size, or the `renderQuantumSize` set in
+/// `options` (see [`NodeConfig::render_quantum_size`]), e.g. for contexts created with a
+/// `renderSizeHint`.
 pub fn create_node<P: Processor>(
-    ctx: &AudioContext,
+    ctx: &BaseAudioContext,
     name: &str,
     data: P::Data,
-    options: Option<&web_sys::AudioWorkletNodeOptions>,
-) -> Result<AudioWorkletNodeWrapper, JsValue> {
+    options: Option<&AudioWorkletNodeOptions>,
+) -> Result<AudioWorkletNodeWrapper<P>, JsValue> {
     use web_thread::web::audio_worklet::BaseAudioContextExt;
 
+    if !is_registered(ctx, name) {
+        return Err(JsValue::from_str(&format!(
+            "Processor `{name}` is not registered on this context"
+        )));
+    }
+
+    // Apply the processor's declared configuration, rejecting conflicting options
+    let requested = match options {
+        Some(options) => node_config_from_options(options)?,
+        None => NodeConfig::default(),
+    };
+    let config = P::node_config()
+        .merge(&requested)
+        .map_err(|e| JsValue::from_str(&format!("Invalid options for `{name}`: {e}")))?;
+    let options = options_with_config(options, &config)?;
+
+    // Unknown options are dropped by the browser, so the block size is passed with the data
+    let render_quantum_size = config
+        .render_quantum_size
+        .or_else(|| context_render_quantum_size(ctx))
+        .unwrap_or(RENDER_QUANTUM_SIZE);
+
     // Create the shared active state flag
     let is_active = Arc::new(AtomicBool::new(true));
 
-    // Wrap the user data with the active state
-    let wrapper_data = ProcessorWrapperData {
+    // Create the message channels between the node and the processor
+    let (message_sender, message_receiver) = command_channel();
+    let (event_sender, event_receiver) = event_channel();
+    let error = Arc::new(ErrorSlot::default());
+    let latency = Arc::new(AtomicUsize::new(0));
+
+    // Wrap the user data with the active state and message channels
+    let wrapper_data = ProcessorWrapperData::<P> {
         user_data: data,
         is_active: is_active.clone(),
+        messages: message_receiver,
+        events: event_sender,
+        error: error.clone(),
+        latency: latency.clone(),
+        render_quantum_size,
     };
 
     // Create the node
     let node = ctx
-        .audio_worklet_node::<ProcessorWrapper<P>>(name, wrapper_data, options)
+        .audio_worklet_node::<ProcessorWrapper<P>>(name, wrapper_data, Some(&options))
         .map_err(|e| JsValue::from_str(&format!("Failed to create node: {:?}", e)))?;
 
     // Return the wrapped node with the shared active state
-    Ok(AudioWorkletNodeWrapper::new(node, is_active))
+    AudioWorkletNodeWrapper::new(
+        node,
+        is_active,
+        message_sender,
+        event_receiver,
+        error,
+        latency,
+    )
+}
+
+/// Reads the port and channel configuration requested in `options`.
+pub(crate) fn node_config_from_options(
+    options: &AudioWorkletNodeOptions,
+) -> Result<NodeConfig, JsValue> {
+    let output_channel_count = options.get_output_channel_count().map(|counts| {
+        counts
+            .iter()
+            .map(|count| count.as_f64().unwrap_or(0.0) as u32)
+            .collect()
+    });
+    let channel_count_mode = Reflect::get(options, &"channelCountMode".into())?
+        .as_string()
+        .map(|mode| mode.parse())
+        .transpose()
+        .map_err(|e: String| JsValue::from_str(&e))?;
+    let channel_interpretation = Reflect::get(options, &"channelInterpretation".into())?
+        .as_string()
+        .map(|interpretation| interpretation.parse())
+        .transpose()
+        .map_err(|e: String| JsValue::from_str(&e))?;
+    let render_quantum_size = Reflect::get(options, &"renderQuantumSize".into())?
+        .as_f64()
+        .map(|size| size as usize);
+
+    Ok(NodeConfig {
+        number_of_inputs: options.get_number_of_inputs(),
+        number_of_outputs: options.get_number_of_outputs(),
+        output_channel_count,
+        channel_count: options.get_channel_count(),
+        channel_count_mode,
+        channel_interpretation,
+        render_quantum_size,
+    })
+}
+
+/// Reads the context's `renderQuantumSize`, on browsers that support `renderSizeHint`.
+fn context_render_quantum_size(ctx: &BaseAudioContext) -> Option<usize> {
+    Reflect::get(ctx, &"renderQuantumSize".into())
+        .ok()?
+        .as_f64()
+        .map(|size| size as usize)
+}
+
+/// Copies `options` and applies `config` on top of it, keeping other fields such as
+/// `parameterData`.
+fn options_with_config(
+    options: Option<&AudioWorkletNodeOptions>,
+    config: &NodeConfig,
+) -> Result<AudioWorkletNodeOptions, JsValue> {
+    let merged = AudioWorkletNodeOptions::new();
+    if let Some(options) = options {
+        Object::assign(&merged, options);
+    }
+
+    if let Some(number_of_inputs) = config.number_of_inputs {
+        merged.set_number_of_inputs(number_of_inputs);
+    }
+    if let Some(number_of_outputs) = config.number_of_outputs {
+        merged.set_number_of_outputs(number_of_outputs);
+    }
+    if let Some(counts) = &config.output_channel_count {
+        let counts: Array = counts.iter().map(|&count| JsValue::from(count)).collect();
+        merged.set_output_channel_count(&counts);
+    }
+    if let Some(channel_count) = config.channel_count {
+        merged.set_channel_count(channel_count);
+    }
+    if let Some(mode) = config.channel_count_mode {
+        Reflect::set(&merged, &"channelCountMode".into(), &mode.into())?;
+    }
+    if let Some(interpretation) = config.channel_interpretation {
+        Reflect::set(
+            &merged,
+            &"channelInterpretation".into(),
+            &interpretation.into(),
+        )?;
+    }
+
+    Ok(merged)
 }
@@ -0,0 +1,283 @@
+use std::cell::UnsafeCell;
+use std::mem::{self, MaybeUninit};
+use std::ptr;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+/// A wait-free single-producer, single-consumer ring buffer in shared memory.
+///
+/// The buffer is split into a [`Producer`] and a [`Consumer`] that can be moved to different
+/// threads, e.g. the producer stays on the main thread while the consumer is passed to the
+/// processor through [`Processor::Data`](crate::Processor::Data), or the other way around.
+/// Neither half allocates, locks or blocks after creation, so both are safe to use in
+/// [`Processor::process`](crate::Processor::process).
+///
+/// Any value can be moved through the buffer one at a time with [`Producer::push`] and
+/// [`Consumer::pop`]; copying whole slices needs `T: Copy`.
+///
+/// # Example
+/// ```
+/// use waw::RingBuffer;
+///
+/// let (mut producer, mut consumer) = RingBuffer::<f32>::new(1024).split();
+///
+/// // Main thread: feed decoded audio.
+/// let written = producer.push_slice(&[0.1, 0.2, 0.3]);
+/// assert_eq!(written, 3);
+///
+/// // Processor: read as much as is available.
+/// let mut block = [0.0; 128];
+/// let read = consumer.pop_slice(&mut block);
+/// assert_eq!(&block[..read], &[0.1, 0.2, 0.3]);
+/// ```
+pub struct RingBuffer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// State shared by the two halves of a ring buffer.
+///
+/// `head` and `tail` count every value ever read and written; the slot of a position is the
+/// position modulo the capacity, and the number of stored values is `tail - head`.
+struct Shared<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    /// Position of the next value to read, only written by the consumer.
+    head: AtomicUsize,
+    /// Position of the next value to write, only written by the producer.
+    tail: AtomicUsize,
+}
+
+// SAFETY: The producer only writes slots between `tail` and `head + capacity`, the consumer only
+// reads slots between `head` and `tail`, and ownership of a slot is handed over by publishing
+// the new position with release ordering.
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns a pointer to the slot at `index`, valid for the rest of the slots.
+    fn slot(&self, index: usize) -> *mut T {
+        // SAFETY: `index` is always reduced modulo the capacity by the callers.
+        unsafe { UnsafeCell::raw_get(self.slots.as_ptr().add(index)).cast() }
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        if !mem::needs_drop::<T>() {
+            return;
+        }
+        let head = *self.head.get_mut();
+        let count = self.tail.get_mut().wrapping_sub(head);
+        for offset in 0..count {
+            // SAFETY: The values between `head` and `tail` were written and never read.
+            unsafe {
+                self.slot(head.wrapping_add(offset) % self.capacity())
+                    .drop_in_place()
+            };
+        }
+    }
+}
+
+impl<T> RingBuffer<T> {
+    /// Creates a ring buffer that holds up to `capacity` values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(
+            capacity > 0,
+            "Ring buffer capacity must be greater than zero"
+        );
+        let slots = (0..capacity)
+            .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            .collect();
+
+        Self {
+            shared: Arc::new(Shared {
+                slots,
+                head: AtomicUsize::new(0),
+                tail: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Splits the ring buffer into its producer and consumer halves.
+    pub fn split(self) -> (Producer<T>, Consumer<T>) {
+        (
+            Producer {
+                shared: self.shared.clone(),
+            },
+            Consumer {
+                shared: self.shared,
+            },
+        )
+    }
+}
+
+/// The writing half of a [`RingBuffer`].
+pub struct Producer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Producer<T> {
+    /// Returns the number of values the buffer can hold.
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+
+    /// Returns the number of values that can currently be written.
+    pub fn free_len(&self) -> usize {
+        let head = self.shared.head.load(Ordering::Acquire);
+        let tail = self.shared.tail.load(Ordering::Relaxed);
+        self.capacity() - tail.wrapping_sub(head)
+    }
+
+    /// Returns `true` if no value can currently be written.
+    pub fn is_full(&self) -> bool {
+        self.free_len() == 0
+    }
+
+    /// Writes a value, or gives it back if the buffer is full.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(value);
+        }
+
+        let tail = self.shared.tail.load(Ordering::Relaxed);
+        // SAFETY: The slot at `tail` is free and only written by this producer.
+        unsafe { self.shared.slot(tail % self.capacity()).write(value) };
+
+        self.shared
+            .tail
+            .store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+}
+
+impl<T: Copy> Producer<T> {
+    /// Writes as many values from `values` as fit, returning how many were written.
+    pub fn push_slice(&mut self, values: &[T]) -> usize {
+        let count = values.len().min(self.free_len());
+        if count == 0 {
+            return 0;
+        }
+
+        let capacity = self.capacity();
+        let tail = self.shared.tail.load(Ordering::Relaxed);
+        let start = tail % capacity;
+        let first = count.min(capacity - start);
+
+        // SAFETY: The `count` slots after `tail` are free and only written by this producer.
+        unsafe {
+            ptr::copy_nonoverlapping(values.as_ptr(), self.shared.slot(start), first);
+            ptr::copy_nonoverlapping(values[first..].as_ptr(), self.shared.slot(0), count - first);
+        }
+
+        self.shared
+            .tail
+            .store(tail.wrapping_add(count), Ordering::Release);
+        count
+    }
+}
+
+/// The reading half of a [`RingBuffer`].
+pub struct Consumer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Consumer<T> {
+    /// Returns the number of values the buffer can hold.
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+
+    /// Returns the number of values that can currently be read.
+    pub fn len(&self) -> usize {
+        let head = self.shared.head.load(Ordering::Relaxed);
+        let tail = self.shared.tail.load(Ordering::Acquire);
+        tail.wrapping_sub(head)
+    }
+
+    /// Returns `true` if no value can currently be read.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a reference to the next value without reading it, if any.
+    pub fn peek(&self) -> Option<&T> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let head = self.shared.head.load(Ordering::Relaxed);
+        // SAFETY: The slot at `head` was published by the producer and is not written again
+        // until this consumer advances `head`, which needs a mutable borrow.
+        Some(unsafe { &*self.shared.slot(head % self.capacity()) })
+    }
+
+    /// Reads the next value, if any.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let head = self.shared.head.load(Ordering::Relaxed);
+        // SAFETY: The slot at `head` was published by the producer and is not written again
+        // until this consumer advances `head`, so the value can be moved out.
+        let value = unsafe { self.shared.slot(head % self.capacity()).read() };
+
+        self.shared
+            .head
+            .store(head.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+}
+
+impl<T: Copy> Consumer<T> {
+    /// Reads as many values as are available into `values`, returning how many were read.
+    pub fn pop_slice(&mut self, values: &mut [T]) -> usize {
+        // SAFETY: `T: Copy`, so overwriting initialized values without dropping them is sound.
+        let values = unsafe { &mut *(values as *mut [T] as *mut [MaybeUninit<T>]) };
+        self.pop_slice_uninit(values)
+    }
+
+    /// Discards up to `count` values, returning how many were discarded.
+    pub fn skip(&mut self, count: usize) -> usize {
+        let count = count.min(self.len());
+        let head = self.shared.head.load(Ordering::Relaxed);
+        self.shared
+            .head
+            .store(head.wrapping_add(count), Ordering::Release);
+        count
+    }
+
+    fn pop_slice_uninit(&mut self, values: &mut [MaybeUninit<T>]) -> usize {
+        let count = values.len().min(self.len());
+        if count == 0 {
+            return 0;
+        }
+
+        let capacity = self.capacity();
+        let head = self.shared.head.load(Ordering::Relaxed);
+        let start = head % capacity;
+        let first = count.min(capacity - start);
+
+        // SAFETY: The `count` slots after `head` were published by the producer and are not
+        // written again until this consumer advances `head`.
+        unsafe {
+            let dst = values.as_mut_ptr().cast::<T>();
+            ptr::copy_nonoverlapping(self.shared.slot(start), dst, first);
+            ptr::copy_nonoverlapping(self.shared.slot(0), dst.add(first), count - first);
+        }
+
+        self.shared
+            .head
+            .store(head.wrapping_add(count), Ordering::Release);
+        count
+    }
+}
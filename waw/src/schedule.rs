@@ -0,0 +1,122 @@
+use crate::{
+    buffer::{InputBuffer, OutputBuffer, ParameterBuffer},
+    parameter::Parameters,
+    processor::{ProcessContext, Processor},
+};
+use std::collections::VecDeque;
+
+/// Number of scheduled messages that can wait for their frame.
+///
+/// The queue never grows, as it is filled on the audio thread.
+pub(crate) const QUEUE_CAPACITY: usize = 1024;
+
+/// When a scheduled message is delivered to [`Processor::handle_event`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timestamp {
+    /// A frame position on the audio context clock, as in `currentFrame`.
+    Frame(u64),
+    /// A time in seconds on the audio context clock, as in `currentTime`.
+    Time(f64),
+}
+
+impl Timestamp {
+    /// Returns the frame position of the timestamp, rounding times to the nearest frame.
+    pub fn to_frame(self, sample_rate: f32) -> u64 {
+        match self {
+            Timestamp::Frame(frame) => frame,
+            Timestamp::Time(time) => (time * sample_rate as f64).round().max(0.0) as u64,
+        }
+    }
+}
+
+/// Messages waiting to be delivered at a given frame, ordered by frame.
+pub(crate) struct EventQueue<M> {
+    events: VecDeque<(u64, M)>,
+}
+
+impl<M> EventQueue<M> {
+    pub(crate) fn new() -> Self {
+        Self {
+            events: VecDeque::with_capacity(QUEUE_CAPACITY),
+        }
+    }
+
+    /// Returns `true` if no more messages can be queued.
+    pub(crate) fn is_full(&self) -> bool {
+        self.events.len() == QUEUE_CAPACITY
+    }
+
+    /// Queues a message for `frame`, after any messages already queued for the same frame.
+    /// Gives the message back if the queue is full, rather than growing it.
+    pub(crate) fn push(&mut self, frame: u64, message: M) -> Result<(), M> {
+        if self.is_full() {
+            return Err(message);
+        }
+        let index = self.events.partition_point(|(queued, _)| *queued <= frame);
+        self.events.insert(index, (frame, message));
+        Ok(())
+    }
+
+    /// Drops every queued message.
+    pub(crate) fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// Returns the frame of the next queued message.
+    pub(crate) fn next_frame(&self) -> Option<u64> {
+        self.events.front().map(|(frame, _)| *frame)
+    }
+
+    /// Removes the next message if it is due at or before `frame`.
+    pub(crate) fn pop_due(&mut self, frame: u64) -> Option<M> {
+        if self.next_frame()? <= frame {
+            self.events.pop_front().map(|(_, message)| message)
+        } else {
+            None
+        }
+    }
+}
+
+/// Renders one block, splitting it at the frames of queued messages.
+///
+/// Messages due at the start of each sub-block are delivered to [`Processor::handle_event`]
+/// before it is rendered; messages scheduled in the past are delivered at the start of the block.
+pub(crate) fn render_block<P: Processor>(
+    processor: &mut P,
+    queue: &mut EventQueue<P::Message>,
+    inputs: &mut InputBuffer,
+    outputs: &mut OutputBuffer,
+    params: &ParameterBuffer,
+    context: &ProcessContext,
+) {
+    let mut offset = 0;
+    while offset < context.block_size {
+        let frame = context.current_frame + offset as u64;
+        while let Some(message) = queue.pop_due(frame) {
+            processor.handle_event(message);
+        }
+
+        let end = queue.next_frame().map_or(context.block_size, |next| {
+            (next - context.current_frame).min(context.block_size as u64) as usize
+        });
+        let sub_context = ProcessContext {
+            sample_rate: context.sample_rate,
+            current_frame: frame,
+            current_time: context.time_at(offset),
+            block_size: end - offset,
+        };
+
+        let mut input_refs = inputs.refs_range(offset..end);
+        let mut output_refs = outputs.refs_mut_range(offset..end);
+        let values = P::Params::values(params.get_ref_range(offset..end));
+
+        processor.process_ports(
+            &input_refs.ports(),
+            &mut output_refs.ports(),
+            &sub_context,
+            &values,
+        );
+
+        offset = end;
+    }
+}
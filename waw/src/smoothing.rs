@@ -0,0 +1,183 @@
+/// Smoothing applied to a parameter's values before they are handed to the processor.
+///
+/// Set in [`ParameterDescriptor::smoothing`](crate::ParameterDescriptor::smoothing) to remove
+/// zipper noise when a parameter jumps, e.g. when the UI writes `param.value` directly.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Smoothing {
+    /// Values are used as they are.
+    #[default]
+    None,
+    /// Ramps linearly to each new value over `time` seconds.
+    Linear {
+        /// Length of the ramp, in seconds.
+        time: f32,
+    },
+    /// Follows each new value with a one-pole low-pass filter.
+    Exponential {
+        /// Time constant of the filter, in seconds.
+        time: f32,
+    },
+    /// Ramps geometrically to each new value over `time` seconds, which sounds even for
+    /// frequencies and gains.
+    ///
+    /// Jumps instead when either value is not positive.
+    Multiplicative {
+        /// Length of the ramp, in seconds.
+        time: f32,
+    },
+}
+
+/// Relative distance at which an exponential smoother snaps to its target.
+const SETTLE_THRESHOLD: f32 = 1e-4;
+
+/// Smooths a stream of target values, one frame at a time.
+///
+/// Used by the parameter buffers for parameters declared with [`Smoothing`], and usable
+/// directly for values that are not parameters.
+///
+/// # Example
+/// ```
+/// use waw::{Smoother, Smoothing};
+///
+/// let mut gain = Smoother::new(Smoothing::Linear { time: 0.01 }, 1000.0);
+/// assert_eq!(gain.next(0.0), 0.0);
+/// assert_eq!(gain.next(1.0), 0.1);
+/// assert_eq!(gain.next(1.0), 0.2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Smoother {
+    smoothing: Smoothing,
+    sample_rate: f32,
+    /// Current value, or `None` until the first target is known.
+    current: Option<f32>,
+    target: f32,
+    /// Increment (linear) or factor (multiplicative) applied each frame of a ramp.
+    step: f32,
+    /// Part of the distance to the target covered each frame by exponential smoothing.
+    coefficient: f32,
+    /// Frames left in the current ramp.
+    remaining: usize,
+}
+
+impl Smoother {
+    /// Creates a smoother for `sample_rate`. The first target is used without smoothing.
+    pub fn new(smoothing: Smoothing, sample_rate: f32) -> Self {
+        Self {
+            smoothing,
+            sample_rate,
+            current: None,
+            target: 0.0,
+            step: 0.0,
+            coefficient: exponential_coefficient(smoothing, sample_rate),
+            remaining: 0,
+        }
+    }
+
+    /// Sets the sample rate used to convert smoothing times to frames.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.coefficient = exponential_coefficient(self.smoothing, sample_rate);
+    }
+
+    /// Returns the current value, or `None` if no target has been given yet.
+    pub fn value(&self) -> Option<f32> {
+        self.current
+    }
+
+    /// Returns `true` if the smoother has reached its target.
+    pub fn is_settled(&self) -> bool {
+        self.current == Some(self.target)
+    }
+
+    /// Jumps to the next target without smoothing.
+    pub fn reset(&mut self) {
+        self.current = None;
+        self.remaining = 0;
+    }
+
+    /// Jumps to `value` and makes it the target, e.g. when values were used unsmoothed.
+    pub fn snap(&mut self, value: f32) {
+        self.current = Some(value);
+        self.target = value;
+        self.remaining = 0;
+    }
+
+    /// Returns the smoothed value for the next frame, moving towards `target`.
+    pub fn next(&mut self, target: f32) -> f32 {
+        let Some(current) = self.current else {
+            self.current = Some(target);
+            self.target = target;
+            return target;
+        };
+        if target != self.target {
+            self.start(current, target);
+        }
+
+        let next = match self.smoothing {
+            Smoothing::None => target,
+            Smoothing::Linear { .. } | Smoothing::Multiplicative { .. } => {
+                if self.remaining > 1 {
+                    self.remaining -= 1;
+                    if matches!(self.smoothing, Smoothing::Linear { .. }) {
+                        current + self.step
+                    } else {
+                        current * self.step
+                    }
+                } else {
+                    self.remaining = 0;
+                    target
+                }
+            }
+            Smoothing::Exponential { .. } => {
+                let next = current + (target - current) * self.coefficient;
+                if (target - next).abs() <= SETTLE_THRESHOLD * target.abs().max(SETTLE_THRESHOLD) {
+                    target
+                } else {
+                    next
+                }
+            }
+        };
+
+        self.current = Some(next);
+        next
+    }
+
+    /// Smooths a block of target values into `output`.
+    pub fn process(&mut self, targets: &[f32], output: &mut [f32]) {
+        for (target, output) in targets.iter().zip(output) {
+            *output = self.next(*target);
+        }
+    }
+
+    /// Starts a ramp from `current` to `target`.
+    fn start(&mut self, current: f32, target: f32) {
+        self.target = target;
+        match self.smoothing {
+            Smoothing::Linear { time } => {
+                self.remaining = self.frames(time);
+                self.step = (target - current) / self.remaining as f32;
+            }
+            Smoothing::Multiplicative { time } if current > 0.0 && target > 0.0 => {
+                self.remaining = self.frames(time);
+                self.step = (target / current).powf(1.0 / self.remaining as f32);
+            }
+            _ => self.remaining = 0,
+        }
+    }
+
+    /// Converts a ramp time to a number of frames, at least one.
+    fn frames(&self, time: f32) -> usize {
+        ((time * self.sample_rate).round() as usize).max(1)
+    }
+}
+
+/// Returns the one-pole coefficient of exponential smoothing at `sample_rate`, or 1 for other
+/// kinds of smoothing.
+fn exponential_coefficient(smoothing: Smoothing, sample_rate: f32) -> f32 {
+    match smoothing {
+        Smoothing::Exponential { time } if time * sample_rate > 0.0 => {
+            1.0 - (-1.0 / (time * sample_rate)).exp()
+        }
+        _ => 1.0,
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{
+    buffer::{InputBuffer, OutputBuffer},
+    processor::Processor,
+};
+
+/// Keeps a deactivated processor rendering until its tail has finished.
+///
+/// Once the node wrapper is dropped, the processor keeps rendering while its inputs are audible,
+/// for [`Processor::tail_time`] after they go silent and while [`Processor::is_ringing`]. Its
+/// output is then faded out over [`Processor::fade_out_time`], or the node's fade length, before
+/// it stops.
+pub(crate) struct Release {
+    /// Frames rendered since the inputs went silent.
+    silent_frames: u64,
+    /// Frames of the fade-out rendered so far, once the tail has finished.
+    faded_frames: Option<usize>,
+}
+
+impl Release {
+    pub(crate) fn new() -> Self {
+        Self {
+            silent_frames: 0,
+            faded_frames: None,
+        }
+    }
+
+    /// Updates the release after a block has been rendered, fading `outputs` if the tail has
+    /// finished.
+    ///
+    /// Returns `true` once the processor can stop rendering.
+    pub(crate) fn end_block<P: Processor>(
+        &mut self,
+        processor: &P,
+        inputs: &mut InputBuffer,
+        outputs: &mut OutputBuffer,
+        sample_rate: f32,
+        default_fade_frames: usize,
+    ) -> bool {
+        let block_size = inputs.buffer_size();
+        let fade_frames = processor
+            .fade_out_time()
+            .map_or(default_fade_frames, |time| {
+                (time * sample_rate as f64).round() as usize
+            });
+
+        if let Some(faded_frames) = &mut self.faded_frames {
+            for channel in outputs.refs_mut().iter_mut() {
+                for (i, sample) in channel.iter_mut().enumerate() {
+                    let position = *faded_frames + i;
+                    *sample *= if position < fade_frames {
+                        1.0 - position as f32 / fade_frames as f32
+                    } else {
+                        0.0
+                    };
+                }
+            }
+            *faded_frames += block_size;
+            return *faded_frames >= fade_frames;
+        }
+
+        let silent = inputs
+            .refs()
+            .iter()
+            .all(|channel| channel.iter().all(|&sample| sample == 0.0));
+        if silent {
+            self.silent_frames += block_size as u64;
+        } else {
+            self.silent_frames = 0;
+        }
+
+        let tail_frames = (processor.tail_time() * sample_rate as f64).ceil() as u64;
+        if self.silent_frames < tail_frames || processor.is_ringing() {
+            return false;
+        }
+
+        // The tail has finished: fade out from the next block
+        self.faded_frames = Some(0);
+        fade_frames == 0
+    }
+}
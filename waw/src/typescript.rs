@@ -0,0 +1,300 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+
+use crate::parameter::{AutomationRate, ParameterDescriptor};
+
+/// TypeScript declaration entry for a processor, submitted by [`register!`](crate::register).
+///
+/// Unlike `registry::ProcessorRegistration`, declarations are collected on every target, so
+/// [`declarations`] can run natively at build time.
+pub struct ProcessorDeclaration {
+    /// The name the processor is registered under
+    pub name: &'static str,
+    /// Returns the processor's parameter descriptors
+    pub parameter_descriptors: fn() -> Vec<ParameterDescriptor>,
+    /// Returns the TypeScript type of the processor's messages, see [`TypeScript`]
+    pub message_type: fn(&mut BTreeSet<String>) -> String,
+    /// Returns the TypeScript type of the processor's events, see [`TypeScript`]
+    pub event_type: fn(&mut BTreeSet<String>) -> String,
+}
+
+impl ProcessorDeclaration {
+    /// Creates a new `ProcessorDeclaration` for the processor registered as `name`.
+    pub const fn new(
+        name: &'static str,
+        parameter_descriptors: fn() -> Vec<ParameterDescriptor>,
+        message_type: fn(&mut BTreeSet<String>) -> String,
+        event_type: fn(&mut BTreeSet<String>) -> String,
+    ) -> Self {
+        Self {
+            name,
+            parameter_descriptors,
+            message_type,
+            event_type,
+        }
+    }
+}
+
+inventory::collect!(ProcessorDeclaration);
+
+/// A Rust type with a TypeScript equivalent, used to declare message and event types.
+///
+/// Implemented for numbers, `bool`, strings, `()` (as `never`), references, `Box`, `Option`,
+/// `Vec`, slices, arrays, tuples and maps. Types exported by `wasm-bindgen`, such as a
+/// `#[wasm_bindgen]` enum, implement it with [`typescript_binding!`](crate::typescript_binding),
+/// which also imports them from the bindings.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` has no TypeScript declaration",
+    note = "implement `waw::typescript::TypeScript`, or use `waw::typescript_binding!` for types exported by `wasm-bindgen`"
+)]
+pub trait TypeScript {
+    /// Returns the TypeScript type, adding the names it needs from the `wasm-bindgen`
+    /// bindings to `imports`.
+    fn typescript_type(imports: &mut BTreeSet<String>) -> String;
+}
+
+/// Returns the TypeScript type for the Rust type `T`.
+pub fn typescript_type<T: TypeScript + ?Sized>() -> String {
+    T::typescript_type(&mut BTreeSet::new())
+}
+
+macro_rules! impl_typescript {
+    ($typescript:literal: $($ty:ty),*) => {
+        $(
+            impl TypeScript for $ty {
+                fn typescript_type(_imports: &mut BTreeSet<String>) -> String {
+                    $typescript.to_string()
+                }
+            }
+        )*
+    };
+}
+
+impl_typescript!("never": ());
+impl_typescript!("boolean": bool);
+impl_typescript!("number": f32, f64, i8, i16, i32, u8, u16, u32, isize, usize);
+impl_typescript!("bigint": i64, u64, i128, u128);
+impl_typescript!("string": str, String);
+
+impl<T: TypeScript + ?Sized> TypeScript for &T {
+    fn typescript_type(imports: &mut BTreeSet<String>) -> String {
+        T::typescript_type(imports)
+    }
+}
+
+impl<T: TypeScript + ?Sized> TypeScript for Box<T> {
+    fn typescript_type(imports: &mut BTreeSet<String>) -> String {
+        T::typescript_type(imports)
+    }
+}
+
+impl<T: TypeScript> TypeScript for Option<T> {
+    fn typescript_type(imports: &mut BTreeSet<String>) -> String {
+        format!("{} | undefined", T::typescript_type(imports))
+    }
+}
+
+/// Returns the TypeScript array type with elements of type `element`.
+fn array_type(element: String) -> String {
+    // `|` binds looser than `[]`, so unions need parentheses
+    if element.contains('|') {
+        format!("({element})[]")
+    } else {
+        format!("{element}[]")
+    }
+}
+
+impl<T: TypeScript> TypeScript for Vec<T> {
+    fn typescript_type(imports: &mut BTreeSet<String>) -> String {
+        array_type(T::typescript_type(imports))
+    }
+}
+
+impl<T: TypeScript> TypeScript for [T] {
+    fn typescript_type(imports: &mut BTreeSet<String>) -> String {
+        array_type(T::typescript_type(imports))
+    }
+}
+
+impl<T: TypeScript, const N: usize> TypeScript for [T; N] {
+    fn typescript_type(imports: &mut BTreeSet<String>) -> String {
+        array_type(T::typescript_type(imports))
+    }
+}
+
+macro_rules! impl_typescript_tuple {
+    ($($ty:ident),+) => {
+        impl<$($ty: TypeScript),+> TypeScript for ($($ty,)+) {
+            fn typescript_type(imports: &mut BTreeSet<String>) -> String {
+                let elements = [$($ty::typescript_type(imports)),+];
+                format!("[{}]", elements.join(", "))
+            }
+        }
+    };
+}
+
+impl_typescript_tuple!(A);
+impl_typescript_tuple!(A, B);
+impl_typescript_tuple!(A, B, C);
+impl_typescript_tuple!(A, B, C, D);
+
+impl<K: TypeScript, V: TypeScript, S> TypeScript for HashMap<K, V, S> {
+    fn typescript_type(imports: &mut BTreeSet<String>) -> String {
+        let key = K::typescript_type(imports);
+        format!("Map<{key}, {}>", V::typescript_type(imports))
+    }
+}
+
+impl<K: TypeScript, V: TypeScript> TypeScript for BTreeMap<K, V> {
+    fn typescript_type(imports: &mut BTreeSet<String>) -> String {
+        let key = K::typescript_type(imports);
+        format!("Map<{key}, {}>", V::typescript_type(imports))
+    }
+}
+
+/// Returns a TypeScript literal type for `value`, or `number` if it has none.
+fn number_literal(value: f32) -> String {
+    if value.is_finite() {
+        format!("{value}")
+    } else {
+        "number".to_string()
+    }
+}
+
+/// Generates TypeScript declarations for every processor registered with
+/// [`register!`](crate::register).
+///
+/// The declarations describe each processor name, its `AudioParam` names with their default,
+/// minimum and maximum values and display metadata, and the Rust types of its messages and
+/// events. They also provide `TypedAudioWorkletNode<"name">`, whose `parameters.get` only accepts
+/// the processor's parameter names. Types declared with
+/// [`typescript_binding!`](crate::typescript_binding) are imported from `bindings`, the module
+/// generated by `wasm-bindgen`.
+pub fn declarations(bindings: &str) -> String {
+    let mut imports = BTreeSet::new();
+    let mut processors: Vec<_> = inventory::iter::<ProcessorDeclaration>()
+        .map(|declaration| {
+            (
+                declaration.name,
+                (declaration.parameter_descriptors)(),
+                (declaration.message_type)(&mut imports),
+                (declaration.event_type)(&mut imports),
+            )
+        })
+        .collect();
+    processors.sort_by_key(|(name, ..)| *name);
+    processors.dedup_by_key(|(name, ..)| *name);
+
+    let mut out = String::new();
+    out.push_str("// Generated by waw from the registered processors. Do not edit.\n\n");
+    if !imports.is_empty() {
+        let imports: Vec<_> = imports.into_iter().collect();
+        let _ = writeln!(
+            out,
+            "import type {{ {} }} from {bindings:?};\n",
+            imports.join(", ")
+        );
+    }
+
+    out.push_str("/** AudioParams of each registered processor. */\n");
+    out.push_str("export interface ProcessorParameters {\n");
+    for (name, descriptors, ..) in &processors {
+        let _ = writeln!(out, "  {name:?}: {{");
+        for descriptor in descriptors {
+            let automation_rate = match descriptor.automation_rate {
+                AutomationRate::ARate => "a-rate",
+                AutomationRate::KRate => "k-rate",
+            };
+            let _ = write!(
+                out,
+                "    {:?}: {{ defaultValue: {}; minValue: {}; maxValue: {}; automationRate: {automation_rate:?}; ",
+                descriptor.name,
+                number_literal(descriptor.default_value),
+                number_literal(descriptor.min_value),
+                number_literal(descriptor.max_value),
+            );
+            let label = descriptor.label.as_deref().unwrap_or(&descriptor.name);
+            let _ = write!(out, "label: {label:?}; ");
+            if let Some(unit) = &descriptor.unit {
+                let _ = write!(out, "unit: {unit:?}; ");
+            }
+            let _ = write!(out, "scale: {:?}; ", descriptor.scale.as_str());
+            if let Some(step) = descriptor.step {
+                let _ = write!(out, "step: {}; ", number_literal(step));
+            }
+            let choices: Vec<_> = descriptor
+                .choices
+                .iter()
+                .map(|choice| format!("{choice:?}"))
+                .collect();
+            let _ = writeln!(out, "choices: [{}] }};", choices.join(", "));
+        }
+        out.push_str("  };\n");
+    }
+    out.push_str("}\n\n");
+
+    out.push_str(
+        "/**\n * Rust types of the messages and events of each registered processor, for reference.\n \
+         * Messages and events stay on the Rust side: they are sent with \
+         `AudioWorkletNodeWrapper::send`\n * and received with `set_on_event`, and can only \
+         reach JS through `#[wasm_bindgen]` methods.\n */\n",
+    );
+    out.push_str("export interface ProcessorMessages {\n");
+    for (name, _, message, event) in &processors {
+        let _ = writeln!(out, "  {name:?}: {{ message: {message}; event: {event} }};");
+    }
+    out.push_str("}\n\n");
+
+    out.push_str(
+        r#"/** Name of a registered processor. */
+export type ProcessorName = keyof ProcessorParameters;
+
+/** Name of an AudioParam of processor `P`. */
+export type ParameterName<P extends ProcessorName> = keyof ProcessorParameters[P] & string;
+
+/** A parameter descriptor with its display metadata, as returned by `parameterDescriptors()`. */
+export interface ParameterMetadata {
+  name: string;
+  defaultValue: number;
+  minValue: number;
+  maxValue: number;
+  automationRate: "a-rate" | "k-rate";
+  label: string;
+  unit?: string;
+  scale: "linear" | "logarithmic" | "exponential";
+  step?: number;
+  choices: string[];
+}
+
+/** An `AudioParamMap` that only accepts the parameter names of processor `P`. */
+export type TypedAudioParamMap<P extends ProcessorName> = Omit<AudioParamMap, "get" | "has"> & {
+  get(name: ParameterName<P>): AudioParam;
+  has(name: ParameterName<P>): true;
+};
+
+/** An `AudioWorkletNode` running processor `P`. */
+export type TypedAudioWorkletNode<P extends ProcessorName> = Omit<AudioWorkletNode, "parameters"> & {
+  readonly parameters: TypedAudioParamMap<P>;
+};
+"#,
+    );
+
+    out
+}
+
+/// Writes the output of [`declarations`] to `path`.
+///
+/// # Example
+/// ```ignore
+/// // src/bin/declarations.rs, run after `wasm-pack build`
+/// use my_crate as _; // Links the processors registered in the library
+///
+/// fn main() -> std::io::Result<()> {
+///     waw::typescript::write_declarations("pkg/processors.d.ts", "./my_crate")
+/// }
+/// ```
+pub fn write_declarations(path: impl AsRef<Path>, bindings: &str) -> io::Result<()> {
+    std::fs::write(path, declarations(bindings))
+}
@@ -1,22 +1,41 @@
 use crate::{
-    buffer::{InputBuffer, OutputBuffer, ParameterBuffer},
-    processor::Processor,
+    buffer::{block_size_from_js, InputBuffer, OutputBuffer, ParameterBuffer},
+    error::{self, ErrorSlot},
+    fade::{Declick, DEFAULT_FADE_FRAMES},
+    message::{self, Command, EventSender},
+    processor::{ProcessContext, Processor},
+    registry::node_config_from_options,
+    ring::Consumer,
+    schedule::{self, EventQueue},
+    tail::Release,
 };
-use js_sys::{Array, Iterator, Object};
+use js_sys::{Array, Float32Array, Iterator, Object};
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
+    atomic::{AtomicBool, AtomicUsize, Ordering},
     Arc,
 };
-use wasm_bindgen::JsCast;
-use web_sys::{AudioWorkletGlobalScope, AudioWorkletNodeOptions, AudioWorkletProcessor};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{
+    AudioWorkletGlobalScope, AudioWorkletNodeOptions, AudioWorkletProcessor, MessagePort,
+};
 use web_thread::web::audio_worklet::ExtendAudioWorkletProcessor;
 
 /// Internal data structure that wraps user data with lifecycle management.
-pub struct ProcessorWrapperData<D> {
+pub struct ProcessorWrapperData<P: Processor> {
     /// The user's processor data
-    pub user_data: D,
+    pub user_data: P::Data,
     /// Shared flag indicating if the processor should continue processing
     pub is_active: Arc<AtomicBool>,
+    /// Messages sent from the main thread
+    pub(crate) messages: Consumer<Command<P::Message>>,
+    /// Events posted back to the main thread
+    pub(crate) events: EventSender<P::Event>,
+    /// Error reported to the node if the processor fails
+    pub(crate) error: Arc<ErrorSlot>,
+    /// Latency reported by the processor, in frames
+    pub(crate) latency: Arc<AtomicUsize>,
+    /// Number of frames in the blocks the node is expected to render
+    pub(crate) render_quantum_size: usize,
 }
 
 /// A wrapper struct for a type implementing the `Processor` trait, used to interface with the Web Audio API.
@@ -26,13 +45,31 @@ pub struct ProcessorWrapper<P: Processor> {
     output_buffer: OutputBuffer,
     parameter_buffer: ParameterBuffer,
     is_active: Arc<AtomicBool>,
+    messages: Consumer<Command<P::Message>>,
+    /// Scheduled messages waiting for their frame.
+    queue: EventQueue<P::Message>,
+    events: EventSender<P::Event>,
+    /// Port used to notify the main thread that events are waiting.
+    port: Option<MessagePort>,
+    error: Arc<ErrorSlot>,
+    latency: Arc<AtomicUsize>,
+    /// Block size [`Processor::prepare`] was last called with, or 0 before the first block.
+    prepared_block_size: usize,
+    /// Number of frames in the blocks the node is expected to render.
+    render_quantum_size: usize,
+    /// Fade-in and bypass applied to the processor's output.
+    declick: Declick,
+    /// Tail of the processor, once the node wrapper has been dropped.
+    release: Option<Release>,
+    /// Set once [`Processor::on_deactivate`] has been called.
+    deactivated: bool,
 }
 
 impl<P: Processor> ExtendAudioWorkletProcessor for ProcessorWrapper<P> {
-    type Data = ProcessorWrapperData<P::Data>;
+    type Data = ProcessorWrapperData<P>;
 
     fn new(
-        _this: AudioWorkletProcessor,
+        this: AudioWorkletProcessor,
         data: Option<Self::Data>,
         options: AudioWorkletNodeOptions,
     ) -> Self {
@@ -40,26 +77,19 @@ impl<P: Processor> ExtendAudioWorkletProcessor for ProcessorWrapper<P> {
         let processor = P::new(wrapper_data.user_data);
         let is_active = wrapper_data.is_active;
 
-        // Initialize with minimal buffers - they will dynamically resize on first process() call
-        // based on what JavaScript provides in the inputs/outputs arrays.
-        // Web Audio API typically uses 128 samples per render quantum.
-        let initial_buffer_size = 128;
-
-        let channel_count = options.get_channel_count().unwrap_or(1);
-        let input_count = options.get_number_of_inputs().unwrap_or(0);
-        let output_count = options.get_number_of_outputs().unwrap_or(1);
-
-        let input_buffer = InputBuffer::new(
-            (input_count * channel_count).try_into().unwrap(),
-            initial_buffer_size,
-        );
+        // Preallocate for the configured ports and render quantum size. Buffers still resize
+        // in process() if JavaScript provides a different number of channels, e.g. with `max`
+        // mixing, or a different number of frames.
+        let render_quantum_size = wrapper_data.render_quantum_size;
 
-        let output_buffer = OutputBuffer::new(
-            (output_count * channel_count).try_into().unwrap(),
-            initial_buffer_size,
-        );
+        let config = node_config_from_options(&options).unwrap_or_default();
+        let input_buffer =
+            InputBuffer::new(config.input_layout().iter().sum(), render_quantum_size);
+        let output_buffer =
+            OutputBuffer::new(config.output_layout().iter().sum(), render_quantum_size);
 
-        let parameter_buffer = ParameterBuffer::new();
+        let parameter_buffer =
+            ParameterBuffer::new(&P::parameter_descriptors(), render_quantum_size);
 
         Self {
             processor,
@@ -67,48 +97,176 @@ impl<P: Processor> ExtendAudioWorkletProcessor for ProcessorWrapper<P> {
             output_buffer,
             parameter_buffer,
             is_active,
+            messages: wrapper_data.messages,
+            queue: EventQueue::new(),
+            events: wrapper_data.events,
+            port: this.port().ok(),
+            error: wrapper_data.error,
+            latency: wrapper_data.latency,
+            prepared_block_size: 0,
+            render_quantum_size,
+            declick: Declick::new(DEFAULT_FADE_FRAMES),
+            release: None,
+            deactivated: false,
         }
     }
 
     fn process(&mut self, inputs: Array, outputs: Array, parameters: Object) -> bool {
-        if !self.is_active.load(Ordering::Acquire) {
+        if self.deactivated {
             return false;
         }
 
+        // Keep rendering after the node wrapper is dropped, until the tail has finished
+        if self.release.is_none() && !self.is_active.load(Ordering::Acquire) {
+            self.release = Some(Release::new());
+        }
+
+        // Contain panics to this processor's node, see `ProcessorError`
+        let error = self.error.clone();
+        match error::catch_panics(&error, || self.render(&inputs, &outputs, &parameters)) {
+            Some(running) => {
+                if !running {
+                    self.deactivated = true;
+                }
+                running
+            }
+            None => {
+                // The processor may be left inconsistent, so it is never called again
+                self.deactivated = true;
+                silence(&outputs);
+                // No `processorerror` is fired for a caught panic
+                self.notify();
+                false
+            }
+        }
+    }
+
+    fn parameter_descriptors() -> Iterator {
+        let arr = Array::new();
+        for desc in P::parameter_descriptors() {
+            arr.push(&desc.into());
+        }
+        arr.values()
+    }
+}
+
+impl<P: Processor> ProcessorWrapper<P> {
+    /// Delivers messages, renders one block and posts events back to the main thread.
+    ///
+    /// Returns `false` once the processor has stopped after being deactivated.
+    fn render(&mut self, inputs: &Array, outputs: &Array, parameters: &Object) -> bool {
         let global: AudioWorkletGlobalScope = js_sys::global().unchecked_into();
         let sample_rate = global.sample_rate();
 
+        // Read the block size from the arrays, as inputs may be disconnected
+        let block_size = block_size_from_js(outputs)
+            .or_else(|| block_size_from_js(inputs))
+            .unwrap_or(self.render_quantum_size);
+
+        // Prepare before the first block, and again if a block is larger than announced
+        if block_size > self.prepared_block_size {
+            if self.prepared_block_size == 0 {
+                self.parameter_buffer.set_sample_rate(sample_rate);
+            }
+            self.prepared_block_size = block_size.max(self.render_quantum_size);
+            self.processor
+                .prepare(sample_rate, self.prepared_block_size);
+        }
+
+        // Deliver messages from the main thread before rendering, queueing scheduled ones
+        message::deliver_commands(
+            &mut self.messages,
+            &mut self.processor,
+            &mut self.queue,
+            &mut self.declick,
+            sample_rate,
+        );
+
         // Fill input buffers from JS, handling resizing and zeroing
-        self.input_buffer.fill_from_js(&inputs);
+        self.input_buffer.ensure_size(block_size);
+        self.input_buffer.fill_from_js(inputs);
 
         // Ensure output buffer matches the configuration from JS
         self.output_buffer
             .ensure_size(self.input_buffer.buffer_size());
-        self.output_buffer.ensure_channels_from_js(&outputs);
+        self.output_buffer.ensure_channels_from_js(outputs);
         self.output_buffer.clear();
 
-        self.parameter_buffer.fill_from_js(&parameters);
+        // Keep parameter buffers the size of the block before filling them
+        self.parameter_buffer
+            .set_block_size(self.input_buffer.buffer_size());
+        self.parameter_buffer.fill_from_js(parameters);
+        self.parameter_buffer.smooth();
+
+        let context = ProcessContext {
+            sample_rate,
+            current_frame: global.current_frame() as u64,
+            current_time: global.current_time(),
+            block_size: self.input_buffer.buffer_size(),
+        };
+
+        // Process audio, keeping channels grouped by port and splitting at scheduled messages
+        schedule::render_block(
+            &mut self.processor,
+            &mut self.queue,
+            &mut self.input_buffer,
+            &mut self.output_buffer,
+            &self.parameter_buffer,
+            &context,
+        );
 
-        // Get references for processing
-        let input_refs = self.input_buffer.get_refs();
-        let mut output_refs = self.output_buffer.get_mut_refs();
-        let params = self.parameter_buffer.get_ref();
+        self.declick
+            .apply(&mut self.input_buffer, &mut self.output_buffer);
 
-        // Process audio
-        self.processor
-            .process(&input_refs, &mut output_refs, sample_rate, &params);
+        let stopped = match &mut self.release {
+            Some(release) => release.end_block(
+                &self.processor,
+                &mut self.input_buffer,
+                &mut self.output_buffer,
+                sample_rate,
+                self.declick.fade_frames(),
+            ),
+            None => false,
+        };
 
         // Copy output data back to JS
-        self.output_buffer.copy_to_js(&outputs);
+        self.output_buffer.copy_to_js(outputs);
+
+        // Post events back to the main thread, only notifying it when the first event of a
+        // batch is waiting, as each notification allocates a message
+        let mut notify = false;
+        while let Some(event) = self.processor.poll_event() {
+            notify |= self.events.send(event);
+        }
+        if notify {
+            self.notify();
+        }
+
+        // Publish the latency, e.g. after a lookahead is resized; the main thread polls it
+        self.latency
+            .store(self.processor.latency(), Ordering::Release);
 
-        true
+        if stopped {
+            self.processor.on_deactivate();
+        }
+        !stopped
     }
 
-    fn parameter_descriptors() -> Iterator {
-        let arr = Array::new();
-        for desc in P::parameter_descriptors() {
-            arr.push(&desc.into());
+    /// Notifies the main thread that events are waiting or the processor failed.
+    fn notify(&self) {
+        if let Some(port) = &self.port {
+            let _ = port.post_message(&JsValue::NULL);
+        }
+    }
+}
+
+/// Zeros every channel of a JS Array of output ports.
+fn silence(outputs: &Array) {
+    for port in outputs.iter() {
+        let channels: Array = port.unchecked_into();
+        for channel in channels.iter() {
+            let channel: Float32Array = channel.unchecked_into();
+            channel.fill(0.0, 0, channel.length());
         }
-        arr.values()
     }
 }
@@ -0,0 +1,147 @@
+//! Checks that the steady-state render path performs no heap allocations.
+//!
+//! Only the harness path is exercised: it shares the parameter, message, scheduling and
+//! declicking code with the web wrapper, but not the copies to and from JS arrays, which can't
+//! run natively. Those are kept allocation-free by construction rather than by this test.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use waw::{
+    AutomationRate, ParameterDescriptor, ParameterValuesRef, ProcessContext, Processor,
+    RenderHarness, Smoothing, Timestamp, RENDER_QUANTUM_SIZE,
+};
+
+/// Counts allocations made by the current thread while counting is enabled.
+struct CountingAllocator;
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.with(Cell::get) {
+            ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn count_allocations(f: impl FnOnce()) -> usize {
+    ALLOCATIONS.with(|count| count.set(0));
+    COUNTING.with(|counting| counting.set(true));
+    f();
+    COUNTING.with(|counting| counting.set(false));
+    ALLOCATIONS.with(Cell::get)
+}
+
+/// Pans its input, scaled by a level set by messages, and posts the number of rendered blocks.
+struct StereoGain {
+    level: f32,
+    blocks: usize,
+}
+
+impl Processor for StereoGain {
+    type Data = ();
+    type Message = f32;
+    type Event = usize;
+
+    fn new(_data: Self::Data) -> Self {
+        Self {
+            level: 1.0,
+            blocks: 0,
+        }
+    }
+
+    fn on_message(&mut self, level: f32) {
+        self.level = level;
+    }
+
+    fn poll_event(&mut self) -> Option<usize> {
+        (self.blocks > 0).then(|| std::mem::take(&mut self.blocks))
+    }
+
+    fn process(
+        &mut self,
+        inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        _context: &ProcessContext,
+        params: &ParameterValuesRef,
+    ) {
+        let gain = params.get("gain").unwrap();
+        let pan = params.get("pan").unwrap();
+        for (channel, (input, output)) in inputs.iter().zip(outputs.iter_mut()).enumerate() {
+            let side = if channel == 0 { -1.0 } else { 1.0 };
+            for (i, sample) in output.iter_mut().enumerate() {
+                *sample = input[i] * gain[i] * (1.0 + side * pan[i]) * 0.5 * self.level;
+            }
+        }
+        self.blocks += 1;
+    }
+
+    fn parameter_descriptors() -> Vec<ParameterDescriptor> {
+        vec![
+            ParameterDescriptor {
+                name: "gain".to_string(),
+                default_value: 1.0,
+                min_value: 0.0,
+                max_value: 1.0,
+                automation_rate: AutomationRate::ARate,
+                ..Default::default()
+            },
+            ParameterDescriptor {
+                name: "pan".to_string(),
+                default_value: 0.0,
+                min_value: -1.0,
+                max_value: 1.0,
+                automation_rate: AutomationRate::KRate,
+                smoothing: Smoothing::Linear { time: 0.01 },
+                ..Default::default()
+            },
+        ]
+    }
+}
+
+#[test]
+fn process_block_does_not_allocate() {
+    let mut harness = RenderHarness::<StereoGain>::new((), 48000.0, 2);
+
+    let left = [0.25; RENDER_QUANTUM_SIZE];
+    let right = [0.5; RENDER_QUANTUM_SIZE];
+    let gain: Vec<f32> = (0..RENDER_QUANTUM_SIZE)
+        .map(|i| i as f32 / RENDER_QUANTUM_SIZE as f32)
+        .collect();
+    let mut out_left = [0.0; RENDER_QUANTUM_SIZE];
+    let mut out_right = [0.0; RENDER_QUANTUM_SIZE];
+
+    // The first block sizes the buffers for the channel layout.
+    harness.process_block(&[&left, &right], &mut [&mut out_left, &mut out_right]);
+    harness.take_events();
+
+    let mut events = 0;
+    let allocations = count_allocations(|| {
+        for block in 0..64 {
+            harness.set_parameter("gain", &gain);
+            harness.set_parameter("pan", &[block as f32 / 64.0]);
+            harness.send(block as f32 / 64.0);
+            let frame = harness.current_frame() + RENDER_QUANTUM_SIZE as u64 / 2;
+            harness.schedule(1.0, Timestamp::Frame(frame));
+            harness.process_block(&[&left, &right], &mut [&mut out_left, &mut out_right]);
+            while let Some(blocks) = harness.try_recv() {
+                events += blocks;
+            }
+        }
+    });
+
+    assert_eq!(allocations, 0);
+    // Each block is split in two by its scheduled message
+    assert_eq!(events, 128);
+}
@@ -0,0 +1,87 @@
+//! Checks that `DelayCompensation` delays its input and reports it as latency.
+
+use waw::{
+    DelayCompensation, DelayCompensationData, Processor, RenderHarness, RENDER_QUANTUM_SIZE,
+};
+
+fn impulse(len: usize) -> Vec<f32> {
+    let mut input = vec![0.0; len];
+    input[0] = 1.0;
+    input
+}
+
+fn data(delay: usize) -> DelayCompensationData {
+    DelayCompensationData {
+        delay,
+        max_delay: 256,
+        channels: 2,
+    }
+}
+
+#[test]
+fn delays_input_across_blocks() {
+    let mut harness = RenderHarness::<DelayCompensation>::new(data(200), 48000.0, 2);
+    assert_eq!(harness.processor().latency(), 200);
+
+    let left = impulse(3 * RENDER_QUANTUM_SIZE);
+    let right: Vec<f32> = left.iter().map(|sample| -sample).collect();
+    let outputs = harness.render(left.len(), &[&left, &right], &[]);
+
+    for (output, sign) in outputs.iter().zip([1.0, -1.0]) {
+        assert_eq!(output[200], sign);
+        assert_eq!(output.iter().filter(|&&sample| sample != 0.0).count(), 1);
+    }
+}
+
+#[test]
+fn delay_can_be_changed() {
+    let mut harness = RenderHarness::<DelayCompensation>::new(data(0), 48000.0, 1);
+    let input = impulse(RENDER_QUANTUM_SIZE);
+    assert_eq!(harness.render(input.len(), &[&input], &[])[0][0], 1.0);
+
+    harness.send(64);
+    assert_eq!(harness.render(input.len(), &[&input], &[])[0][64], 1.0);
+    assert_eq!(harness.processor().latency(), 64);
+
+    // Delays beyond the preallocated maximum are clamped
+    harness.send(1000);
+    harness.render(input.len(), &[&input], &[]);
+    assert_eq!(harness.processor().latency(), 256);
+}
+
+#[test]
+fn changing_the_delay_keeps_buffered_audio() {
+    let mut harness = RenderHarness::<DelayCompensation>::new(data(64), 48000.0, 1);
+    let mut input = vec![0.0; RENDER_QUANTUM_SIZE];
+    input[100] = 1.0;
+    let silence = vec![0.0; RENDER_QUANTUM_SIZE];
+    assert!(harness.render(input.len(), &[&input], &[])[0]
+        .iter()
+        .all(|&sample| sample == 0.0));
+
+    // The impulse is still in the delay line, now read 96 frames after it was written
+    harness.send(96);
+    let output = &harness.render(silence.len(), &[&silence], &[])[0];
+    assert_eq!(output[196 - RENDER_QUANTUM_SIZE], 1.0);
+    assert_eq!(output.iter().filter(|&&sample| sample != 0.0).count(), 1);
+}
+
+#[test]
+fn tail_time_is_zero_before_prepare() {
+    let compensation = DelayCompensation::new(data(64));
+    assert_eq!(compensation.tail_time(), 0.0);
+}
+
+#[test]
+fn extra_channels_are_silenced() {
+    let data = DelayCompensationData {
+        channels: 1,
+        ..data(0)
+    };
+    let mut harness = RenderHarness::<DelayCompensation>::new(data, 48000.0, 2);
+    let input = impulse(RENDER_QUANTUM_SIZE);
+
+    let outputs = harness.render(input.len(), &[&input, &input], &[]);
+    assert_eq!(outputs[0], input);
+    assert!(outputs[1].iter().all(|&sample| sample == 0.0));
+}
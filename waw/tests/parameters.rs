@@ -0,0 +1,190 @@
+//! Checks the descriptors and accessors generated by `#[derive(Parameters)]`.
+
+use waw::{
+    AutomationRate, ParameterMapping, ParameterValue, Parameters, ProcessContext, Processor,
+    RenderHarness, Smoothing,
+};
+
+#[derive(Parameters)]
+struct FilterParams<'a> {
+    #[param(default = 1000.0, min = 20.0, max = 20000.0)]
+    cutoff: &'a [f32],
+    #[param(
+        name = "q",
+        default = 1.0,
+        min = 0.1,
+        max = 30.0,
+        automation_rate = "k-rate"
+    )]
+    resonance: &'a [f32],
+    #[param(min = -1.0, max = 1.0, smoothing = "linear", smoothing_time = 64.0 / 48000.0)]
+    mix: &'a [f32],
+}
+
+/// Writes `cutoff * q + mix` to its only output.
+struct TypedProcessor;
+
+impl Processor for TypedProcessor {
+    type Data = ();
+    type Params = FilterParams<'static>;
+
+    fn new(_data: Self::Data) -> Self {
+        Self
+    }
+
+    fn process(
+        &mut self,
+        _inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        _context: &ProcessContext,
+        params: &FilterParams,
+    ) {
+        for (i, sample) in outputs[0].iter_mut().enumerate() {
+            *sample = params.cutoff[i] * params.resonance[i] + params.mix[i];
+        }
+    }
+}
+
+#[test]
+fn derive_generates_descriptors() {
+    let descriptors = TypedProcessor::parameter_descriptors();
+
+    let names: Vec<_> = descriptors.iter().map(|desc| desc.name.as_str()).collect();
+    assert_eq!(names, ["cutoff", "q", "mix"]);
+
+    assert_eq!(descriptors[0].default_value, 1000.0);
+    assert_eq!(descriptors[0].min_value, 20.0);
+    assert_eq!(descriptors[0].max_value, 20000.0);
+    assert!(matches!(
+        descriptors[0].automation_rate,
+        AutomationRate::ARate
+    ));
+    assert!(matches!(
+        descriptors[1].automation_rate,
+        AutomationRate::KRate
+    ));
+    assert_eq!(descriptors[2].default_value, 0.0);
+    assert_eq!(descriptors[2].min_value, -1.0);
+    assert_eq!(descriptors[0].smoothing, Smoothing::None);
+    assert_eq!(
+        descriptors[2].smoothing,
+        Smoothing::Linear {
+            time: 64.0 / 48000.0
+        }
+    );
+}
+
+#[test]
+fn derive_fills_fields_by_index() {
+    let mut harness = RenderHarness::<TypedProcessor>::new((), 48000.0, 1);
+
+    let outputs = harness.render(128, &[], &[("q", &[2.0]), ("mix", &[0.5])]);
+
+    assert!(outputs[0].iter().all(|&sample| sample == 2000.5));
+}
+
+#[test]
+fn smoothed_parameters_ramp_to_new_values() {
+    let mut harness = RenderHarness::<TypedProcessor>::new((), 48000.0, 1);
+    harness.render(128, &[], &[("q", &[1.0]), ("mix", &[0.5])]);
+
+    let outputs = harness.render(128, &[], &[("mix", &[1.0])]);
+
+    assert!(outputs[0][0] > 1000.5 && outputs[0][0] < 1001.0);
+    assert!(outputs[0].windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(outputs[0][63..].iter().all(|&sample| sample == 1001.0));
+}
+
+#[test]
+fn automation_is_not_smoothed() {
+    let mut harness = RenderHarness::<TypedProcessor>::new((), 48000.0, 1);
+    let ramp: Vec<f32> = (0..128).map(|i| i as f32 / 128.0).collect();
+
+    let automated = harness.render(128, &[], &[("q", &[1.0]), ("mix", &ramp)]);
+    for (sample, mix) in automated[0].iter().zip(&ramp) {
+        assert_eq!(*sample, 1000.0 + mix);
+    }
+
+    // The smoother continues from the last automated value, so holding it does not ramp
+    let last = ramp[127];
+    let held = harness.render(128, &[], &[("mix", &[last])]);
+    assert!(held[0].iter().all(|&sample| sample == 1000.0 + last));
+}
+
+#[derive(Parameters)]
+struct GainParams<'a> {
+    #[param(default = 1.0, smoothing = "linear", smoothing_time = 64.0 / 48000.0)]
+    gain: ParameterValue<'a>,
+}
+
+/// Writes the gain if it is constant for the block, and -1.0 otherwise.
+struct ConstantProcessor;
+
+impl Processor for ConstantProcessor {
+    type Data = ();
+    type Params = GainParams<'static>;
+
+    fn new(_data: Self::Data) -> Self {
+        Self
+    }
+
+    fn process(
+        &mut self,
+        _inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        _context: &ProcessContext,
+        params: &GainParams,
+    ) {
+        assert_eq!(params.gain.len(), outputs[0].len());
+        outputs[0].fill(params.gain.constant().unwrap_or(-1.0));
+    }
+}
+
+#[test]
+fn constant_blocks_are_detected() {
+    let mut harness = RenderHarness::<ConstantProcessor>::new((), 48000.0, 1);
+    let automation: Vec<f32> = (0..128).map(|i| i as f32 / 128.0).collect();
+
+    let constant = harness.render(128, &[], &[("gain", &[0.5])]);
+    assert_eq!(constant[0][0], 0.5);
+
+    let automated = harness.render(128, &[], &[("gain", &automation)]);
+    assert_eq!(automated[0][0], -1.0);
+
+    // The jump to the new value is smoothed over this block, then settles
+    let smoothed = harness.render(128, &[], &[("gain", &[0.25])]);
+    assert_eq!(smoothed[0][0], -1.0);
+    let settled = harness.render(128, &[], &[("gain", &[0.25])]);
+    assert_eq!(settled[0][0], 0.25);
+}
+
+#[derive(Parameters)]
+#[allow(dead_code)]
+struct MappedParams<'a> {
+    #[param(default = 1000.0, min = 20.0, max = 20000.0, scale = "logarithmic")]
+    cutoff: &'a [f32],
+    #[param(default = 1.0, min = 0.0, max = 2.0, mapping = "decibels", min_db = -48.0)]
+    gain: &'a [f32],
+    #[param(choices("sine", "square", "saw"))]
+    waveform: &'a [f32],
+}
+
+#[test]
+fn derive_declares_mappings() {
+    let descriptors = MappedParams::descriptors();
+
+    assert_eq!(descriptors[0].mapping, ParameterMapping::Logarithmic);
+    assert_eq!(
+        descriptors[1].mapping,
+        ParameterMapping::Decibels { min_db: -48.0 }
+    );
+    assert_eq!(
+        descriptors[2].mapping,
+        ParameterMapping::Stepped { step: 1.0 }
+    );
+
+    assert_eq!(descriptors[0].from_normalized(0.0), 20.0);
+    assert_eq!(descriptors[1].from_normalized(0.0), 0.0);
+    assert_eq!(descriptors[2].from_normalized(0.6), 1.0);
+    assert_eq!(descriptors[2].to_normalized(2.0), 1.0);
+}
@@ -0,0 +1,99 @@
+//! Checks the ring buffer halves on their own and across threads.
+
+use std::thread;
+use waw::RingBuffer;
+
+#[test]
+fn push_and_pop_respect_capacity() {
+    let (mut producer, mut consumer) = RingBuffer::<u32>::new(4).split();
+
+    assert!(consumer.is_empty());
+    assert_eq!(producer.push_slice(&[1, 2, 3, 4, 5]), 4);
+    assert!(producer.is_full());
+    assert_eq!(producer.push(6), Err(6));
+
+    assert_eq!(consumer.len(), 4);
+    assert_eq!(consumer.pop(), Some(1));
+    assert_eq!(producer.free_len(), 1);
+    assert_eq!(producer.push(5), Ok(()));
+
+    let mut values = [0; 8];
+    assert_eq!(consumer.pop_slice(&mut values), 4);
+    assert_eq!(values[..4], [2, 3, 4, 5]);
+    assert_eq!(consumer.pop(), None);
+}
+
+#[test]
+fn slices_wrap_around_the_end() {
+    let (mut producer, mut consumer) = RingBuffer::<u32>::new(5).split();
+    let mut values = [0; 3];
+
+    for round in 0..10 {
+        let start = round * 3;
+        assert_eq!(producer.push_slice(&[start, start + 1, start + 2]), 3);
+        assert_eq!(consumer.pop_slice(&mut values), 3);
+        assert_eq!(values, [start, start + 1, start + 2]);
+    }
+}
+
+#[test]
+fn skip_discards_values() {
+    let (mut producer, mut consumer) = RingBuffer::<u32>::new(8).split();
+
+    producer.push_slice(&[1, 2, 3]);
+
+    assert_eq!(consumer.skip(2), 2);
+    assert_eq!(consumer.skip(5), 1);
+    assert!(consumer.is_empty());
+    assert_eq!(producer.free_len(), 8);
+}
+
+#[test]
+fn values_are_moved_and_dropped() {
+    let (mut producer, mut consumer) = RingBuffer::<String>::new(2).split();
+
+    assert!(producer.push("a".to_string()).is_ok());
+    assert!(producer.push("b".to_string()).is_ok());
+    assert_eq!(producer.push("c".to_string()), Err("c".to_string()));
+    assert_eq!(consumer.pop().as_deref(), Some("a"));
+
+    // Values still waiting are dropped with the buffer
+    let value = std::rc::Rc::new(());
+    let (mut producer, consumer) = RingBuffer::new(2).split();
+    assert!(producer.push(value.clone()).is_ok());
+    drop((producer, consumer));
+    assert_eq!(std::rc::Rc::strong_count(&value), 1);
+}
+
+#[test]
+fn streams_in_order_across_threads() {
+    const COUNT: u32 = 100_000;
+    let (mut producer, mut consumer) = RingBuffer::<u32>::new(128).split();
+
+    let writer = thread::spawn(move || {
+        let mut next = 0;
+        while next < COUNT {
+            let chunk: Vec<_> = (next..COUNT.min(next + 37)).collect();
+            match producer.push_slice(&chunk) {
+                0 => thread::yield_now(),
+                written => next += written as u32,
+            }
+        }
+    });
+
+    let mut expected = 0;
+    let mut block = [0; 64];
+    while expected < COUNT {
+        let read = consumer.pop_slice(&mut block);
+        if read == 0 {
+            thread::yield_now();
+        }
+        for &value in &block[..read] {
+            assert_eq!(value, expected);
+            expected += 1;
+        }
+    }
+
+    writer.join().unwrap();
+    assert!(consumer.is_empty());
+}
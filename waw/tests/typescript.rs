@@ -0,0 +1,143 @@
+//! Checks the TypeScript declarations generated for registered processors.
+
+use std::collections::{BTreeMap, HashMap};
+use waw::typescript::{declarations, typescript_type};
+use waw::{register, Parameters, ProcessContext, Processor};
+
+#[derive(Parameters)]
+struct SynthParams<'a> {
+    #[param(default = 440.0, min = 20.0, max = 20000.0)]
+    #[param(label = "Frequency", unit = "Hz", scale = "logarithmic")]
+    frequency: &'a [f32],
+    #[param(default = 0.5, min = 0.0, max = 1.0, automation_rate = "k-rate")]
+    gain: &'a [f32],
+    #[param(automation_rate = "k-rate", choices("sine", "square", "saw"))]
+    waveform: &'a [f32],
+}
+
+mod shapes {
+    /// Stands in for a `#[wasm_bindgen]` enum.
+    #[allow(dead_code)]
+    pub enum Shape {
+        Sine,
+    }
+
+    waw::typescript_binding!(Shape);
+}
+
+/// Writes `frequency * gain`, or silence for the `"saw"` waveform, to its outputs.
+struct SynthProcessor;
+
+impl Processor for SynthProcessor {
+    type Data = ();
+    type Params = SynthParams<'static>;
+    type Message = shapes::Shape;
+    type Event = Vec<f32>;
+
+    fn new(_data: Self::Data) -> Self {
+        Self
+    }
+
+    fn process(
+        &mut self,
+        _inputs: &[&[f32]],
+        outputs: &mut [&mut [f32]],
+        _context: &ProcessContext,
+        params: &SynthParams,
+    ) {
+        let gain = if params.waveform[0] == 2.0 {
+            0.0
+        } else {
+            params.gain[0]
+        };
+        for output in outputs.iter_mut() {
+            for (i, sample) in output.iter_mut().enumerate() {
+                *sample = params.frequency[i] * gain;
+            }
+        }
+    }
+}
+
+register!(SynthProcessor, "synth");
+
+// `register!` defines items, so each processor needs its own module
+mod silent {
+    use super::*;
+
+    pub struct SilentProcessor;
+
+    impl Processor for SilentProcessor {
+        type Data = ();
+
+        fn new(_data: Self::Data) -> Self {
+            Self
+        }
+
+        fn process(
+            &mut self,
+            _inputs: &[&[f32]],
+            _outputs: &mut [&mut [f32]],
+            _context: &ProcessContext,
+            _params: &waw::ParameterValuesRef,
+        ) {
+        }
+    }
+
+    register!(SilentProcessor, "silent");
+}
+
+#[test]
+fn declarations_describe_registered_processors() {
+    let declarations = declarations("./synth_bindings");
+
+    assert!(declarations.contains(r#"import type { Shape } from "./synth_bindings";"#));
+    assert!(declarations.contains(
+        r#""frequency": { defaultValue: 440; minValue: 20; maxValue: 20000; automationRate: "a-rate"; label: "Frequency"; unit: "Hz"; scale: "logarithmic"; choices: [] };"#
+    ));
+    assert!(declarations.contains(
+        r#""gain": { defaultValue: 0.5; minValue: 0; maxValue: 1; automationRate: "k-rate"; label: "gain"; scale: "linear"; choices: [] };"#
+    ));
+    assert!(declarations.contains(
+        r#""waveform": { defaultValue: 0; minValue: 0; maxValue: 2; automationRate: "k-rate"; label: "waveform"; scale: "linear"; step: 1; choices: ["sine", "square", "saw"] };"#
+    ));
+    assert!(declarations.contains(r#""synth": { message: Shape; event: number[] };"#));
+    assert!(declarations.contains(r#""silent": { message: never; event: never };"#));
+
+    // Processors are listed by name, so the output is stable across builds
+    assert!(declarations.find(r#""silent""#) < declarations.find(r#""synth""#));
+}
+
+#[test]
+fn rust_types_map_to_typescript() {
+    assert_eq!(typescript_type::<()>(), "never");
+    assert_eq!(typescript_type::<f32>(), "number");
+    assert_eq!(typescript_type::<String>(), "string");
+    assert_eq!(typescript_type::<Option<Vec<u8>>>(), "number[] | undefined");
+    assert_eq!(typescript_type::<shapes::Shape>(), "Shape");
+}
+
+#[test]
+fn nested_types_map_to_typescript() {
+    assert_eq!(
+        typescript_type::<Vec<Option<f32>>>(),
+        "(number | undefined)[]"
+    );
+    assert_eq!(
+        typescript_type::<Vec<Option<Vec<Option<u8>>>>>(),
+        "((number | undefined)[] | undefined)[]"
+    );
+    assert_eq!(typescript_type::<[f32; 4]>(), "number[]");
+    assert_eq!(typescript_type::<&[f32]>(), "number[]");
+    assert_eq!(
+        typescript_type::<(u32, &str, Option<bool>)>(),
+        "[number, string, boolean | undefined]"
+    );
+    assert_eq!(
+        typescript_type::<HashMap<String, Vec<(f32, f32)>>>(),
+        "Map<string, [number, number][]>"
+    );
+    assert_eq!(
+        typescript_type::<BTreeMap<u64, Box<shapes::Shape>>>(),
+        "Map<bigint, Shape>"
+    );
+}